@@ -0,0 +1,108 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use aoc_2023_firmware::net::{NetRunner, NetStack};
+use aoc_2023_firmware::platform::Rp2040;
+use aoc_2023_firmware::usb::{AcmUsb, RawUsb};
+use aoc_2023_firmware::{bsp, info, net, stack};
+use aoc_2023_icd::TCP_PORT;
+use aoc_2023_server::images::{Aoc, BoardServer, READ_SIZE, WRITE_SIZE};
+use aoc_2023_server::repl::Repl;
+use defmt_rtt as _;
+use embassy_executor::Spawner;
+use embassy_net::tcp::TcpSocket;
+use embassy_time::{Duration, Instant};
+use embedded_io_async::Write;
+use panic_probe as _;
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    stack::paint();
+    let p = embassy_rp::init(Default::default());
+    let (mut board, aoc) = bsp::init_aoc(p);
+    spawner.must_spawn(usb_task(RawUsb::new(board.usb.reader, board.usb.writer)));
+    spawner.must_spawn(acm_task(AcmUsb::new(aoc.serial)));
+    spawner.must_spawn(console_task(AcmUsb::new(aoc.console)));
+    let (runner, net_stack) = net::init(aoc.ncm);
+    spawner.must_spawn(ncm_task(runner));
+    spawner.must_spawn(net_task(net_stack));
+    spawner.must_spawn(tcp_task(net_stack));
+    board.usb.usb.run().await;
+}
+
+#[embassy_executor::task]
+async fn usb_task(mut usb: RawUsb) {
+    // Each link keeps its own lines and counters, so all of them can be used at the same time, and a host that lost
+    // the link can pick a run up where it left off.
+    let mut session = Aoc::new(Rp2040);
+    loop {
+        usb.wait_connection().await;
+        info!("Connected");
+        // A fresh server for every connection, so the frame the last host was cut off in the middle of is forgotten.
+        BoardServer::new(&mut usb).serve(&mut session).await;
+        info!("Disconnected");
+    }
+}
+
+#[embassy_executor::task]
+async fn acm_task(mut serial: AcmUsb) {
+    let mut session = Aoc::new(Rp2040);
+    loop {
+        serial.wait_connection().await;
+        info!("Serial port opened");
+        BoardServer::new(&mut serial).serve(&mut session).await;
+        info!("Serial port closed");
+    }
+}
+
+#[embassy_executor::task]
+async fn ncm_task(runner: NetRunner) -> ! {
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn net_task(stack: &'static NetStack) -> ! {
+    stack.run().await
+}
+
+/// How often to probe an idle TCP peer, and how long it may go without answering before the connection is dropped.
+const TCP_KEEP_ALIVE: Duration = Duration::from_secs(5);
+const TCP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Serves the protocol on `TCP_PORT` over the USB ethernet link, to one connection at a time.
+#[embassy_executor::task]
+async fn tcp_task(stack: &'static NetStack) {
+    let mut session = Aoc::new(Rp2040);
+    let mut rx_buffer = [0; READ_SIZE];
+    let mut tx_buffer = [0; WRITE_SIZE];
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        // A peer that goes away without closing the connection stops answering the keep-alives, and the socket is
+        // closed after the timeout, so the port is free for the next connection.
+        socket.set_keep_alive(Some(TCP_KEEP_ALIVE));
+        socket.set_timeout(Some(TCP_TIMEOUT));
+        if socket.accept(TCP_PORT).await.is_err() {
+            continue;
+        }
+        info!("TCP connection from {:?}", socket.remote_endpoint());
+        let mut server = BoardServer::new(socket);
+        server.serve(&mut session).await;
+        let socket = server.stream();
+        socket.close();
+        let _ = socket.flush().await;
+        info!("TCP connection closed");
+    }
+}
+
+/// Serves the text console, keeping the day and the lines fed so far when the terminal is closed.
+#[embassy_executor::task]
+async fn console_task(mut console: AcmUsb) {
+    let mut repl = Repl::new();
+    loop {
+        console.wait_connection().await;
+        info!("Console opened");
+        let _ = repl.run(&mut console, || Instant::now().as_micros()).await;
+        info!("Console closed");
+    }
+}
@@ -1,18 +1,25 @@
 #![no_std]
 #![feature(type_alias_impl_trait)]
 
-pub mod rpc;
-
 pub mod bsp {
+    use core::fmt::Write;
+
     use aoc_2023_icd::{PID, VID};
-    use embassy_rp::peripherals::USB;
+    use embassy_rp::flash::{Blocking, Flash};
+    use embassy_rp::peripherals::{FLASH, USB};
     use embassy_rp::usb::{self, Driver, In, InterruptHandler, Out};
     use embassy_rp::{bind_interrupts, Peripherals};
+    use embassy_usb::class::cdc_acm::{CdcAcmClass, State};
+    use embassy_usb::class::cdc_ncm::{self, CdcNcmClass};
     use embassy_usb::msos::{self, windows_version};
     use embassy_usb::{Builder, Config};
     use static_cell::make_static;
 
     const DEVICE_INTERFACE_GUIDS: &[&str] = &["{AFB9A6FB-30BA-44BC-9232-806CFC875321}"];
+    /// The MAC address of the host's end of the USB ethernet link.
+    const HOST_MAC: [u8; 6] = [0x02, 0xc0, 0xde, 0xca, 0xfe, 0x01];
+    /// The size of the Pico's flash chip.
+    const FLASH_SIZE: usize = 2 * 1024 * 1024;
 
     bind_interrupts!(struct Irqs {
         USBCTRL_IRQ => InterruptHandler<USB>;
@@ -27,13 +34,49 @@ pub mod bsp {
         pub usb: UsbParts,
     }
 
+    /// The USB functions only the `aoc` image serves.
+    pub struct AocParts {
+        /// The CDC-ACM function, carrying the same frames as `reader` and `writer`.
+        pub serial: CdcAcmClass<'static, Driver<'static, USB>>,
+        /// A second CDC-ACM function, for typing at the board in a terminal.
+        pub console: CdcAcmClass<'static, Driver<'static, USB>>,
+        /// A CDC-NCM function, which shows up as an ethernet adapter on the host.
+        pub ncm: CdcNcmClass<'static, Driver<'static, USB>>,
+    }
+
+    /// Sets the board up with just the vendor function, as the single-day images serve nothing else.
     pub fn init(p: Peripherals) -> Board {
+        let (builder, reader, writer) = builder(p);
+        Board {
+            usb: UsbParts { usb: builder.build(), reader, writer },
+        }
+    }
+
+    /// Sets the board up with the serial ports and the ethernet adapter of the `aoc` image as well.
+    pub fn init_aoc(p: Peripherals) -> (Board, AocParts) {
+        let (mut builder, reader, writer) = builder(p);
+
+        // Added after the vendor function, so that one keeps interface 0 and the protocol's serial port gets
+        // `ACM_INTERFACE`, which the host finds it by.
+        let serial = CdcAcmClass::new(&mut builder, make_static!(State::new()), 64);
+        let console = CdcAcmClass::new(&mut builder, make_static!(State::new()), 64);
+        let ncm = CdcNcmClass::new(&mut builder, make_static!(cdc_ncm::State::new()), HOST_MAC, 64);
+
+        let board = Board {
+            usb: UsbParts { usb: builder.build(), reader, writer },
+        };
+        (board, AocParts { serial, console, ncm })
+    }
+
+    /// Starts the USB device with the vendor function, returning its endpoints along with the builder for the
+    /// functions that follow it.
+    fn builder(p: Peripherals) -> (Builder<'static, Driver<'static, USB>>, usb::Endpoint<'static, USB, Out>, usb::Endpoint<'static, USB, In>) {
         let driver = Driver::new(p.USB, Irqs);
 
         let mut config = Config::new(VID, PID);
         config.manufacturer = Some("Embassy");
         config.product = Some("Advent of code 2023");
-        config.serial_number = Some("12345678");
+        config.serial_number = Some(serial_number(p.FLASH));
         config.max_power = 100;
         config.max_packet_size_0 = 64;
         config.device_class = 0xEF;
@@ -45,40 +88,54 @@ pub mod bsp {
             driver,
             config,
             make_static!([0; 256]),
-            make_static!([0; 256]),
+            // The configuration descriptor, which describes every function.
+            make_static!([0; 512]),
             make_static!([0; 256]),
             make_static!([0; 256]),
             make_static!([0; 64]),
         );
 
         builder.msos_descriptor(windows_version::WIN8_1, 0);
-        builder.msos_feature(msos::CompatibleIdFeatureDescriptor::new("WINUSB", ""));
-        builder.msos_feature(msos::RegistryPropertyFeatureDescriptor::new(
+
+        // Add a vendor-specific function (class 0xFF). WinUSB is only bound to this function, so Windows still binds
+        // its own driver to the CDC-ACM one.
+        let mut function = builder.function(0xFF, 0, 0);
+        function.msos_feature(msos::CompatibleIdFeatureDescriptor::new("WINUSB", ""));
+        function.msos_feature(msos::RegistryPropertyFeatureDescriptor::new(
             "DeviceInterfaceGUIDs",
             msos::PropertyData::RegMultiSz(DEVICE_INTERFACE_GUIDS),
         ));
-
-        // Add a vendor-specific function (class 0xFF)
-        let mut function = builder.function(0xFF, 0, 0);
         let mut interface = function.interface();
         let mut alt = interface.alt_setting(0xFF, 0, 0, None);
         let reader = alt.endpoint_bulk_out(64);
         let writer = alt.endpoint_bulk_in(64);
         drop(function);
 
-        let usb = builder.build();
+        (builder, reader, writer)
+    }
 
-        Board {
-            usb: UsbParts { usb, reader, writer },
+    /// The unique ID of the flash chip in hex, so that every board has a serial number of its own to be picked by.
+    fn serial_number(flash: FLASH) -> &'static str {
+        let mut flash = Flash::<_, Blocking, FLASH_SIZE>::new_blocking(flash);
+        let mut id = [0; 8];
+        // Only fails if the flash doesn't answer, which the firmware runs from.
+        flash.blocking_unique_id(&mut id).unwrap();
+        let serial = make_static!(heapless::String::<16>::new());
+        for byte in id {
+            // 8 bytes are 16 hex digits, which is what fits.
+            write!(serial, "{byte:02X}").unwrap();
         }
+        serial
     }
 }
 
 pub mod usb {
     use embassy_rp::peripherals::USB;
-    use embassy_rp::usb::{self, In, Out};
+    use embassy_rp::usb::{self, Driver, In, Out};
+    use embassy_usb::class::cdc_acm::CdcAcmClass;
     use embassy_usb::driver::{Endpoint as _, EndpointIn, EndpointOut};
     use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+
     pub struct RawUsb {
         reader: usb::Endpoint<'static, USB, Out>,
         writer: usb::Endpoint<'static, USB, In>,
@@ -105,12 +162,223 @@ pub mod usb {
 
     impl Write for RawUsb {
         async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-            for chunk in buf.chunks(64) {
+            let size = self.writer.info().max_packet_size as usize;
+            for chunk in buf.chunks(size) {
                 self.writer.write(chunk).await.map_err(|_| ErrorKind::BrokenPipe)?;
             }
+            // As on the CDC-ACM function: the host's transfer only ends on a short packet.
+            if buf.len() % size == 0 {
+                self.writer.write(&[]).await.map_err(|_| ErrorKind::BrokenPipe)?;
+            }
+            Ok(buf.len())
+        }
+    }
+
+    /// The CDC-ACM function, which carries the same COBS frames as `RawUsb` but shows up as a serial port on the host.
+    /// The console port uses it as a plain byte stream.
+    pub struct AcmUsb {
+        class: CdcAcmClass<'static, Driver<'static, USB>>,
+    }
+
+    impl AcmUsb {
+        pub fn new(class: CdcAcmClass<'static, Driver<'static, USB>>) -> Self {
+            Self { class }
+        }
+        /// Waits for the host to open the port.
+        pub async fn wait_connection(&mut self) {
+            self.class.wait_connection().await;
+        }
+    }
+
+    impl ErrorType for AcmUsb {
+        type Error = ErrorKind;
+    }
+
+    impl Read for AcmUsb {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.class.read_packet(buf).await.map_err(|_| ErrorKind::BrokenPipe)
+        }
+    }
+
+    impl Write for AcmUsb {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            let size = self.class.max_packet_size() as usize;
+            for chunk in buf.chunks(size) {
+                self.class.write_packet(chunk).await.map_err(|_| ErrorKind::BrokenPipe)?;
+            }
+            // A full last packet has to be followed by a short one, or the host waits for more.
+            if buf.len() % size == 0 {
+                self.class.write_packet(&[]).await.map_err(|_| ErrorKind::BrokenPipe)?;
+            }
             Ok(buf.len())
         }
     }
+}
+
+pub mod net {
+    use embassy_net::{Config, Ipv4Address, Ipv4Cidr, Stack, StackResources, StaticConfigV4};
+    use embassy_rp::peripherals::USB;
+    use embassy_rp::usb::Driver;
+    use embassy_usb::class::cdc_ncm::embassy_net::{Device, Runner, State};
+    use embassy_usb::class::cdc_ncm::CdcNcmClass;
+    use static_cell::make_static;
+
+    const MTU: usize = 1514;
+    /// The MAC address of the board's end of the USB ethernet link.
+    const BOARD_MAC: [u8; 6] = [0x02, 0xc0, 0xde, 0xca, 0xfe, 0x02];
+    /// The board's address on the USB ethernet link. The host's end needs an address in the same /24.
+    pub const ADDRESS: Ipv4Address = Ipv4Address::new(192, 168, 42, 2);
+
+    pub type NetStack = Stack<Device<'static, MTU>>;
+    pub type NetRunner = Runner<'static, Driver<'static, USB>, MTU>;
+
+    /// Puts a network stack on the CDC-NCM function. Both the runner and the stack have to be run for it to work.
+    pub fn init(ncm: CdcNcmClass<'static, Driver<'static, USB>>) -> (NetRunner, &'static NetStack) {
+        let (runner, device) = ncm.into_embassy_net_device::<MTU, 4, 4>(make_static!(State::new()), BOARD_MAC);
+        let config = Config::ipv4_static(StaticConfigV4 {
+            address: Ipv4Cidr::new(ADDRESS, 24),
+            gateway: None,
+            dns_servers: heapless::Vec::new(),
+        });
+        // Only seeds the choice of ports and sequence numbers, which needn't be hard to guess on a point-to-point link.
+        let seed = 0x2023;
+        let stack = make_static!(Stack::new(device, config, make_static!(StackResources::<2>::new()), seed));
+        (runner, stack)
+    }
+}
+
+pub mod platform {
+    use core::fmt;
+
+    use aoc_2023_icd::log::{Level, LogRecord};
+    use aoc_2023_server::images::{Build, Platform};
+    use embassy_time::Instant;
+
+    use crate::log::RECORDS;
+    use crate::stack;
+
+    /// The board's clock, log and stack, for the dispatchers in `aoc_2023_server::images`.
+    pub struct Rp2040;
+
+    impl Platform for Rp2040 {
+        fn build(&self) -> Build {
+            Build {
+                version: env!("CARGO_PKG_VERSION"),
+                git_hash: env!("GIT_HASH"),
+                profile: env!("BUILD_PROFILE"),
+            }
+        }
+
+        fn now_us(&self) -> u64 {
+            Instant::now().as_micros()
+        }
+
+        fn log(&mut self, level: Level, args: fmt::Arguments) {
+            crate::log::log(level, args)
+        }
+
+        /// Every task's records are queued together, so they are published on whichever link asks first.
+        fn pop_log(&mut self) -> Option<LogRecord> {
+            RECORDS.try_receive().ok()
+        }
+
+        fn peak_stack(&self) -> Option<u32> {
+            Some(stack::peak())
+        }
+    }
+}
+
+pub mod log {
+    use core::fmt::{self, Write};
+
+    pub use aoc_2023_icd::log::Level;
+    use aoc_2023_icd::log::LogRecord;
+    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+    use embassy_sync::channel::Channel;
+    use embassy_time::Instant;
+    use heapless::String;
+
+    /// Records logged but not published yet. Records logged while it is full are only sent to defmt.
+    pub static RECORDS: Channel<CriticalSectionRawMutex, LogRecord, 8> = Channel::new();
+
+    /// Logs to defmt, and queues the record for `images::publish_logs`.
+    pub fn log(level: Level, args: fmt::Arguments) {
+        let mut message = String::new();
+        // A message that doesn't fit is cut short.
+        let _ = message.write_fmt(args);
+        match level {
+            Level::Trace => defmt::trace!("{=str}", message.as_str()),
+            Level::Debug => defmt::debug!("{=str}", message.as_str()),
+            Level::Info => defmt::info!("{=str}", message.as_str()),
+            Level::Warn => defmt::warn!("{=str}", message.as_str()),
+            Level::Error => defmt::error!("{=str}", message.as_str()),
+        }
+        let timestamp_ms = Instant::now().as_millis() as u32;
+        let _ = RECORDS.try_send(LogRecord { level, timestamp_ms, message });
+    }
+
+    /// Like defmt's macros, but formatted with `core::fmt` so the message can be sent to the host as well.
+    #[macro_export]
+    macro_rules! log {
+        ($level:expr, $($arg:tt)*) => {
+            $crate::log::log($level, format_args!($($arg)*))
+        };
+    }
+
+    #[macro_export]
+    macro_rules! debug {
+        ($($arg:tt)*) => { $crate::log!($crate::log::Level::Debug, $($arg)*) };
+    }
+
+    #[macro_export]
+    macro_rules! info {
+        ($($arg:tt)*) => { $crate::log!($crate::log::Level::Info, $($arg)*) };
+    }
+
+    #[macro_export]
+    macro_rules! warn {
+        ($($arg:tt)*) => { $crate::log!($crate::log::Level::Warn, $($arg)*) };
+    }
+
+    #[macro_export]
+    macro_rules! error {
+        ($($arg:tt)*) => { $crate::log!($crate::log::Level::Error, $($arg)*) };
+    }
+}
+
+pub mod stack {
+    use core::ptr::{addr_of, addr_of_mut};
+
+    /// Written over the unused stack at boot, so how deep the stack went can be told later.
+    const PAINT: u32 = 0xC0FF_EE00;
+
+    extern "C" {
+        /// The end of the statics, which the stack grows down towards. Both symbols come from `cortex-m-rt`.
+        static mut __sheap: u32;
+        static _stack_start: u32;
+    }
+
+    /// Paints the stack below the caller. Call it first thing in `main`.
+    #[inline(never)]
+    pub fn paint() {
+        // Stay clear of this function's own frame.
+        let limit = cortex_m::register::msp::read() as usize - 64;
+        let mut p = unsafe { addr_of_mut!(__sheap) };
+        while (p as usize) < limit {
+            unsafe {
+                p.write_volatile(PAINT);
+                p = p.add(1);
+            }
+        }
+    }
 
-    impl super::rpc::RpcServer<1024, 4096> for RawUsb {}
+    /// The most bytes of stack used since `paint`.
+    pub fn peak() -> u32 {
+        let top = unsafe { addr_of!(_stack_start) } as usize;
+        let mut p = unsafe { addr_of!(__sheap) };
+        while (p as usize) < top && unsafe { p.read_volatile() } == PAINT {
+            p = unsafe { p.add(1) };
+        }
+        (top - p as usize) as u32
+    }
 }
@@ -0,0 +1,204 @@
+//! Runs a day through the driver again and again and sums up how fast it went, so protocol changes like batching or
+//! the window size can be compared.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use aoc_2023_icd::stats::{Stats, Timing};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::driver::{self, DriverError, Protocol, RunOptions};
+use crate::emulator::Firmware;
+use crate::transport::Target;
+
+/// What the runs of a benchmark measured. Times are in microseconds, so the JSON form is easy to plot.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub day: u8,
+    pub window: usize,
+    pub runs: u32,
+    /// Lines and bytes of input fed in each run.
+    pub lines: u32,
+    pub bytes: u32,
+    /// Wall time of the runs, from handing the transport to the driver to the answers.
+    pub run_us: Summary,
+    /// Round trip time of the requests carrying lines, over every run.
+    pub latency_us: Summary,
+    pub histogram: Vec<Bucket>,
+    /// From the mean run time.
+    pub lines_per_s: f64,
+    pub bytes_per_s: f64,
+    /// What the board measured in each run, if it serves `GetStats`.
+    pub board: Vec<Stats>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: u64,
+    pub mean: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// The samples that took at most `le_us`, and longer than the bucket before.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Bucket {
+    pub le_us: u64,
+    pub count: usize,
+}
+
+impl Summary {
+    /// Percentiles by the nearest rank.
+    pub fn of(samples: &mut [Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort();
+        let rank = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1].as_micros() as u64;
+        Self {
+            count: samples.len(),
+            min: samples[0].as_micros() as u64,
+            mean: (samples.iter().sum::<Duration>() / samples.len() as u32).as_micros() as u64,
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: samples[samples.len() - 1].as_micros() as u64,
+        }
+    }
+}
+
+/// Buckets doubling in size, up to the first one that holds the slowest sample.
+fn histogram(samples: &[Duration]) -> Vec<Bucket> {
+    let Some(max) = samples.iter().max() else {
+        return Vec::new();
+    };
+    let max = max.as_micros() as u64;
+    let mut buckets = Vec::new();
+    let mut le_us = 1;
+    let mut below = 0;
+    loop {
+        let count = samples.iter().filter(|s| s.as_micros() as u64 <= le_us).count();
+        buckets.push(Bucket { le_us, count: count - below });
+        below = count;
+        if le_us >= max {
+            break;
+        }
+        le_us *= 2;
+    }
+    // Leading empty buckets say nothing.
+    let first = buckets.iter().position(|b| b.count > 0).unwrap_or(0);
+    buckets.split_off(first)
+}
+
+/// Runs `input` through the board at `target` `runs` times, opening it anew each time.
+pub async fn run(target: &Target, firmware: Firmware, protocol: Protocol, day: u8, input: &str, options: &RunOptions, runs: u32) -> Result<Report, DriverError> {
+    let (stats_tx, mut stats_rx) = mpsc::channel(1);
+    let (latency_tx, mut latency_rx) = mpsc::unbounded_channel();
+    let options = RunOptions {
+        stats: Some(stats_tx),
+        latency: Some(latency_tx),
+        ..options.clone()
+    };
+    let mut times = Vec::new();
+    let mut board = Vec::new();
+    for _ in 0..runs {
+        let start = Instant::now();
+        driver::run(target, firmware, protocol, day, input, &options).await?;
+        times.push(start.elapsed());
+        board.extend(stats_rx.try_recv().ok());
+    }
+    let mut latencies = Vec::new();
+    while let Ok(latency) = latency_rx.try_recv() {
+        latencies.push(latency);
+    }
+
+    let lines = input.lines().count() as u32;
+    let bytes = input.len() as u32;
+    let run_us = Summary::of(&mut times);
+    let per_s = |n: u32| if run_us.mean == 0 { 0.0 } else { n as f64 * 1e6 / run_us.mean as f64 };
+    Ok(Report {
+        day,
+        window: options.window,
+        runs,
+        lines,
+        bytes,
+        run_us,
+        latency_us: Summary::of(&mut latencies),
+        histogram: histogram(&latencies),
+        lines_per_s: per_s(lines),
+        bytes_per_s: per_s(bytes),
+        // Stats from only some of the runs would skew the sums.
+        board: if board.len() == times.len() { board } else { Vec::new() },
+    })
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let us = |us: u64| Duration::from_micros(us);
+        write!(
+            f,
+            "min {:?}, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}, mean {:?}",
+            us(self.min),
+            us(self.p50),
+            us(self.p90),
+            us(self.p99),
+            us(self.max),
+            us(self.mean)
+        )
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "day {}, {} runs of {} lines ({} bytes), window {}", self.day, self.runs, self.lines, self.bytes, self.window)?;
+        writeln!(f, "run time: {}", self.run_us)?;
+        writeln!(f, "throughput: {:.0} lines/s, {:.0} bytes/s", self.lines_per_s, self.bytes_per_s)?;
+        writeln!(f, "request latency ({} requests): {}", self.latency_us.count, self.latency_us)?;
+        const WIDTH: usize = 40;
+        let most = self.histogram.iter().map(|b| b.count).max().unwrap_or(0);
+        for bucket in &self.histogram {
+            let bar = (bucket.count * WIDTH).checked_div(most).unwrap_or(0);
+            writeln!(f, "  <= {:>10?} {:<WIDTH$} {}", Duration::from_micros(bucket.le_us), "#".repeat(bar), bucket.count)?;
+        }
+        match self.board.last() {
+            Some(last) => self.fmt_board(f, last),
+            None => writeln!(f, "the board doesn't serve `stats`"),
+        }
+    }
+}
+
+impl Report {
+    /// What the board measured, next to the host's mean time per run.
+    fn fmt_board(&self, f: &mut fmt::Formatter<'_>, last: &Stats) -> fmt::Result {
+        let runs = self.board.len() as u32;
+        let mean = Duration::from_micros(self.run_us.mean);
+        let total = self.board.iter().fold(Timing::default(), |sum, s| Timing {
+            requests: sum.requests + s.total.requests,
+            busy_us: sum.busy_us + s.total.busy_us,
+        });
+        let requests = total.requests / runs;
+        let busy = Duration::from_micros(total.busy_us) / runs;
+        writeln!(
+            f,
+            "board: {requests} requests per run, busy {busy:?} per run ({:.0}% of the host's time)",
+            100.0 * busy.as_secs_f64() / mean.as_secs_f64()
+        )?;
+        if requests > 0 {
+            writeln!(f, "per request: {:?} on the board, {:?} of the host's time", busy / requests, mean / requests)?;
+        }
+        for day in &last.days {
+            let timing = day.timing;
+            writeln!(f, "  day {}: {} requests, busy {:?}", day.day, timing.requests, Duration::from_micros(timing.busy_us))?;
+        }
+        let slowest = self.board.iter().map(|s| s.max_us).max().unwrap_or(0);
+        writeln!(f, "slowest request since the board booted: {:?}", Duration::from_micros(slowest.into()))?;
+        let peak_stack = last.peak_stack.map_or_else(|| "unknown".to_string(), |b| format!("{b} bytes"));
+        writeln!(f, "peak receive buffer: {} bytes, peak stack: {peak_stack}", last.link.peak_used)?;
+        let (overfull, corrupt) = self.board.iter().fold((0, 0), |(o, c), s| (o + s.link.overfull, c + s.link.corrupt));
+        writeln!(f, "frames dropped: {overfull} overfull, {corrupt} corrupt")
+    }
+}
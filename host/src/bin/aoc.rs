@@ -0,0 +1,374 @@
+use std::error::Error;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use aoc_2023_host::bench;
+use aoc_2023_host::driver::{self, Protocol, RunOptions};
+use aoc_2023_host::emulator::{self, Firmware};
+use aoc_2023_host::transport::{AnyTransport, SerialTransport, Target, UsbTransport};
+use aoc_2023_host::verify::Manifest;
+use aoc_2023_icd::log::{Level, LogRecord};
+use aoc_2023_icd::progress::Progress;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Solves Advent of Code 2023 puzzles on the board.
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    target: TargetArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct TargetArgs {
+    /// Use the USB board with this serial number instead of the first one found.
+    #[arg(long, global = true)]
+    serial_number: Option<String>,
+    /// Talk to the USB board through its CDC-ACM serial port rather than its vendor interface.
+    #[arg(long, global = true)]
+    acm: bool,
+    /// Run against a board emulated in-process.
+    #[arg(long, global = true, conflicts_with_all = ["serial_number", "acm", "tcp", "serial"])]
+    emulate: bool,
+    /// Connect to a board over TCP, on port 2023 unless ADDR names one. The board is at 192.168.42.2 on its USB
+    /// ethernet link.
+    #[arg(long, global = true, value_name = "ADDR", conflicts_with_all = ["serial_number", "acm", "serial"])]
+    tcp: Option<String>,
+    /// Connect to a board over a serial port.
+    #[arg(long, global = true, value_name = "PATH", conflicts_with_all = ["serial_number", "acm"])]
+    serial: Option<String>,
+}
+
+impl TargetArgs {
+    fn target(&self) -> Target {
+        let serial_number = self.serial_number.clone();
+        match (self.emulate, &self.tcp, &self.serial) {
+            (true, _, _) => Target::Emulator,
+            (_, Some(addr), _) => Target::Tcp(addr.clone()),
+            (_, _, Some(path)) => Target::Serial(path.clone()),
+            _ if self.acm => Target::Acm { serial_number },
+            _ => Target::Usb { serial_number },
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Solves one day's puzzle.
+    Run(RunArgs),
+    /// Lists the boards connected over USB.
+    ListDevices,
+    /// Runs an emulated board on a pseudo-terminal and prints its path, to test the serial transport without a
+    /// board: `aoc --serial PATH ...` talks to it like to the board's CDC-ACM port.
+    ServePty {
+        /// Emulate the single-day image for this day instead of the `aoc` one.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=3))]
+        day: Option<u8>,
+        /// Serve the text console of the `aoc` image instead, to try it with a terminal or `socat`.
+        #[arg(long, conflicts_with = "day")]
+        console: bool,
+    },
+    /// Runs an emulated board serving the connections to a TCP port one at a time, to test the TCP transport without a
+    /// board: `aoc --tcp ADDR ...` talks to it like to the board's USB ethernet link.
+    ServeTcp {
+        #[arg(long, default_value = "127.0.0.1:2023")]
+        listen: String,
+        /// Emulate the single-day image for this day instead of the `aoc` one.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=3))]
+        day: Option<u8>,
+    },
+    /// Shows what the board can solve.
+    Info,
+    /// Solves one day's puzzle repeatedly and reports how long it took.
+    Bench {
+        #[command(flatten)]
+        run: RunArgs,
+        #[arg(long, default_value_t = 10)]
+        runs: u32,
+        /// Also write the report as JSON to this file.
+        #[arg(long, value_name = "PATH")]
+        json: Option<PathBuf>,
+    },
+    /// Solves every input in the answers manifest and fails if any answer differs from the recorded one.
+    Verify {
+        #[arg(long, default_value = "../input/answers.toml")]
+        manifest: PathBuf,
+        /// Only check the inputs for this day.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=25))]
+        day: Option<u8>,
+        /// Firmware the board is flashed with. With `day`, the board must be flashed for the days checked.
+        #[arg(long, value_enum, default_value_t = FirmwareArg::Aoc)]
+        firmware: FirmwareArg,
+    },
+}
+
+#[derive(Args)]
+struct RunArgs {
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=25))]
+    day: u8,
+    /// Puzzle input file, or `-` for stdin.
+    #[arg(long, default_value = "-")]
+    input: String,
+    /// Firmware the board is flashed with.
+    #[arg(long, value_enum, default_value_t = FirmwareArg::Aoc)]
+    firmware: FirmwareArg,
+    /// Most requests to keep in flight. 1 waits for every response before sending the next request.
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    window: u16,
+    /// Seconds to wait for each response before taking the link to the board to be gone.
+    #[arg(long, default_value_t = 5)]
+    timeout: u64,
+    /// How many times to open the board again and start over when the link is lost, like when it is unplugged.
+    #[arg(long, default_value_t = 3)]
+    reconnects: u32,
+    /// Least severe level of the board's log records to print to stderr.
+    #[arg(long, value_enum, default_value_t = LogLevel::Warn)]
+    log_level: LogLevel,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn min(self) -> Option<Level> {
+        match self {
+            LogLevel::Off => None,
+            LogLevel::Error => Some(Level::Error),
+            LogLevel::Warn => Some(Level::Warn),
+            LogLevel::Info => Some(Level::Info),
+            LogLevel::Debug => Some(Level::Debug),
+            LogLevel::Trace => Some(Level::Trace),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FirmwareArg {
+    /// The combined `aoc` image.
+    Aoc,
+    /// The single-day `dayN` image.
+    Day,
+}
+
+impl RunArgs {
+    fn protocol(&self) -> Protocol {
+        match self.firmware {
+            FirmwareArg::Aoc => Protocol::Solve,
+            FirmwareArg::Day => Protocol::Native,
+        }
+    }
+
+    fn firmware(&self) -> Firmware {
+        match self.firmware {
+            FirmwareArg::Aoc => Firmware::Aoc,
+            FirmwareArg::Day => Firmware::Day(self.day),
+        }
+    }
+
+    fn options(&self) -> RunOptions {
+        RunOptions {
+            window: self.window.into(),
+            timeout: Duration::from_secs(self.timeout),
+            reconnects: self.reconnects,
+            ..Default::default()
+        }
+    }
+
+    fn read_input(&self) -> std::io::Result<String> {
+        match self.input.as_str() {
+            "-" => {
+                let mut input = String::new();
+                std::io::stdin().read_to_string(&mut input)?;
+                Ok(input)
+            }
+            path => std::fs::read_to_string(path),
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let target = cli.target.target();
+
+    match cli.command {
+        Command::Run(run) => {
+            let input = run.read_input()?;
+            let mut options = run.options();
+            let (link_lost, mut lost) = mpsc::unbounded_channel();
+            options.link_lost = Some(link_lost);
+            tokio::spawn(async move {
+                let clear = if std::io::stderr().is_terminal() { "\r\x1b[K" } else { "" };
+                while let Some(e) = lost.recv().await {
+                    eprintln!("{clear}lost the link to the board ({e:?}), opening it again");
+                }
+            });
+            let progress = std::io::stderr().is_terminal().then(|| {
+                let (tx, rx) = mpsc::channel(8);
+                options.progress = Some(tx);
+                tokio::spawn(show_progress(rx, input.lines().count()))
+            });
+            let logs = run.log_level.min().map(|min| {
+                let (tx, rx) = mpsc::channel(32);
+                options.logs = Some(tx);
+                tokio::spawn(show_logs(rx, min))
+            });
+            let answers = driver::run(&target, run.firmware(), run.protocol(), run.day, &input, &options).await;
+            drop(options);
+            if let Some(progress) = progress {
+                progress.await?;
+            }
+            if let Some(logs) = logs {
+                logs.await?;
+            }
+            let answers = answers.map_err(|e| format!("error solving day {}: {e}", run.day))?;
+            println!("Result A: {}", answers.a);
+            if let Some(b) = answers.b {
+                println!("Result B: {b}");
+            }
+        }
+        Command::ListDevices => {
+            for device in UsbTransport::list().map_err(|e| format!("error listing devices: {e:?}"))? {
+                let port = device.serial_number().and_then(|serial_number| SerialTransport::find(Some(serial_number)).ok());
+                println!(
+                    "bus {:03} device {:03}: {} (serial number {}, serial port {})",
+                    device.bus_number(),
+                    device.device_address(),
+                    device.product_string().unwrap_or("unknown"),
+                    device.serial_number().unwrap_or("unknown"),
+                    port.as_deref().unwrap_or("none"),
+                );
+            }
+        }
+        Command::ServePty { day, console } => {
+            let firmware = day.map_or(Firmware::Aoc, Firmware::Day);
+            let (port, path) = emulator::open_pty().map_err(|e| format!("error opening a pseudo-terminal: {e}"))?;
+            println!("{path}");
+            if console {
+                emulator::serve_console(port).await.map_err(|e| format!("error serving the console: {e}"))?;
+            } else {
+                emulator::serve_stream(firmware, port).await;
+            }
+        }
+        Command::ServeTcp { listen, day } => {
+            let firmware = day.map_or(Firmware::Aoc, Firmware::Day);
+            let listener = TcpListener::bind(&listen).await.map_err(|e| format!("error listening on {listen}: {e}"))?;
+            println!("{}", listener.local_addr()?);
+            emulator::serve_tcp(firmware, listener).await?;
+        }
+        Command::Info => {
+            let transport = AnyTransport::open(&target, Firmware::Aoc).await.map_err(|e| format!("error opening transport: {e:?}"))?;
+            let info = driver::info(transport).await.map_err(|e| format!("error querying board: {e}"))?;
+            let days = info.days.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+            println!("Firmware: {} ({}, {} build)", info.firmware_version, info.git_hash, info.profile);
+            println!("Protocol version: {}", info.protocol_version);
+            println!("Days: {}", days.join(", "));
+            println!("Buffers: {} bytes in, {} bytes out", info.read_size, info.write_size);
+            println!("Endpoints:");
+            let known = driver::known_endpoints();
+            for keys in &info.endpoints {
+                let status = match known.iter().find(|k| k.path == keys.path) {
+                    Some(k) if k == keys => "ok",
+                    Some(_) => "schema differs from host",
+                    None => "unknown to host",
+                };
+                println!("  {}: {status}", keys.path);
+            }
+        }
+        Command::Bench { run, runs, json } => {
+            let input = run.read_input()?;
+            let report = bench::run(&target, run.firmware(), run.protocol(), run.day, &input, &run.options(), runs)
+                .await
+                .map_err(|e| format!("error solving day {}: {e}", run.day))?;
+            print!("{report}");
+            if let Some(path) = json {
+                std::fs::write(&path, serde_json::to_string_pretty(&report)?).map_err(|e| format!("error writing {}: {e}", path.display()))?;
+            }
+        }
+        Command::Verify { manifest, day, firmware } => {
+            let manifest = Manifest::load(&manifest).map_err(|e| format!("error reading {}: {e}", manifest.display()))?;
+            let cases = manifest.input.iter().filter(|case| day.is_none() || day == Some(case.day)).collect::<Vec<_>>();
+            let mut failed = 0;
+            for case in &cases {
+                let name = format!("{} (day {})", case.file.display(), case.day);
+                let input = std::fs::read_to_string(&case.file).map_err(|e| format!("error reading {}: {e}", case.file.display()))?;
+                let (protocol, firmware) = match firmware {
+                    FirmwareArg::Aoc => (Protocol::Solve, Firmware::Aoc),
+                    FirmwareArg::Day => (Protocol::Native, Firmware::Day(case.day)),
+                };
+                match driver::run(&target, firmware, protocol, case.day, &input, &RunOptions::default()).await {
+                    Ok(answers) => {
+                        let mismatches = case.check(&answers);
+                        if mismatches.is_empty() {
+                            println!("{name}: ok");
+                        } else {
+                            failed += 1;
+                            println!("{name}: FAILED");
+                            for mismatch in mismatches {
+                                println!("  {mismatch}");
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        println!("{name}: FAILED\n  {e}");
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(format!("{failed} of {} inputs failed", cases.len()).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the board's log records at `min` or above to stderr, clearing the progress bar's line first so that it is
+/// redrawn below them.
+async fn show_logs(mut records: mpsc::Receiver<LogRecord>, min: Level) {
+    let clear = if std::io::stderr().is_terminal() { "\r\x1b[K" } else { "" };
+    while let Some(record) = records.recv().await {
+        if record.level >= min {
+            eprintln!(
+                "{clear}[{:>8.3} {:<5}] {}",
+                record.timestamp_ms as f64 / 1000.0,
+                format!("{:?}", record.level).to_uppercase(),
+                record.message
+            );
+        }
+    }
+}
+
+/// Draws a progress bar on stderr from the board's updates, until they stop.
+async fn show_progress(mut updates: mpsc::Receiver<Progress>, total: usize) {
+    const WIDTH: usize = 30;
+    let mut drawn = false;
+    while let Some(progress) = updates.recv().await {
+        let done = (progress.lines as usize).min(total);
+        let filled = (done * WIDTH).checked_div(total).unwrap_or(WIDTH);
+        let b = progress.answers.b.map_or_else(|| "-".to_string(), |b| b.to_string());
+        eprint!(
+            "\r[{}{}] {done}/{total} lines, A: {}, B: {b}, {} bytes free, up {:.1} s\x1b[K",
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            progress.answers.a,
+            progress.free,
+            progress.uptime_ms as f64 / 1000.0
+        );
+        drawn = true;
+    }
+    if drawn {
+        eprintln!();
+    }
+}
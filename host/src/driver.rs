@@ -0,0 +1,356 @@
+//! Runs a whole puzzle input through a board and collects the answers.
+//!
+//! `solve` talks to the combined `aoc` firmware through the `Solve` endpoint every day shares. The `dayN` drivers
+//! talk to the single-day firmware images in their own protocols.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+use aoc_2023_icd as icd;
+use aoc_2023_icd::chunk::LINE_SIZE;
+use aoc_2023_icd::error::{WireError, ERROR_PATH};
+use aoc_2023_icd::info::{EndpointKeys, GetInfo, Info, PROTOCOL_VERSION};
+use aoc_2023_icd::log::{LogRecord, LogTopic};
+use aoc_2023_icd::progress::{Progress, ProgressTopic};
+use aoc_2023_icd::solver::{Answers, ListDays};
+use aoc_2023_icd::stats::{GetStats, Stats};
+use postcard::experimental::schema::Schema;
+use postcard_rpc::host_client::{HostClient, HostErr};
+use postcard_rpc::{Endpoint, Topic};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::emulator::Firmware;
+use crate::rpc::{self, Pipeline, RequestError};
+use crate::transport::{AnyTransport, Target, Transport, TransportError};
+
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod solve;
+
+#[derive(Debug)]
+pub enum DriverError {
+    Transport(TransportError),
+    /// The board answered with something unexpected, or hung up.
+    Protocol,
+    /// The board failed the request.
+    Device(WireError),
+    UnknownDay(u8),
+    Incompatible(Incompatibility),
+    /// Line `line` (counting from 1) is `len` bytes long, more than the board reassembles.
+    LineTooLong {
+        line: usize,
+        len: usize,
+    },
+}
+
+/// Why the host refuses to talk to a board.
+#[derive(Debug)]
+pub enum Incompatibility {
+    /// The board doesn't answer `GetInfo`, so it runs firmware older than the host.
+    NoInfo,
+    ProtocolVersion {
+        board: u16,
+        host: u16,
+    },
+    /// The board doesn't serve the endpoint at all.
+    MissingEndpoint(String),
+    /// The board serves the endpoint with a different request or response type.
+    SchemaMismatch(String),
+}
+
+impl fmt::Display for DriverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DriverError::Transport(e) => write!(f, "transport error: {e:?}"),
+            DriverError::Protocol => write!(f, "unexpected answer from the board"),
+            DriverError::Device(e) => write!(f, "the board failed the request: {e:?}"),
+            DriverError::UnknownDay(day) => write!(f, "no driver for day {day}"),
+            DriverError::Incompatible(i) => write!(f, "incompatible firmware, reflash the board: {i}"),
+            DriverError::LineTooLong { line, len } => write!(f, "line {line} is {len} bytes long, the board takes lines of at most {LINE_SIZE} bytes"),
+        }
+    }
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Incompatibility::NoInfo => write!(f, "the board doesn't describe itself, so its firmware predates this host"),
+            Incompatibility::ProtocolVersion { board, host } => write!(f, "the board speaks protocol version {board}, the host speaks {host}"),
+            Incompatibility::MissingEndpoint(path) => write!(f, "the board doesn't serve `{path}`"),
+            Incompatibility::SchemaMismatch(path) => write!(f, "the board serves `{path}` with different message types"),
+        }
+    }
+}
+
+impl From<HostErr<WireError>> for DriverError {
+    fn from(e: HostErr<WireError>) -> Self {
+        match e {
+            HostErr::Wire(e) => DriverError::Device(e),
+            HostErr::Closed => DriverError::Transport(TransportError::Closed),
+            HostErr::BadResponse | HostErr::Postcard(_) => DriverError::Protocol,
+        }
+    }
+}
+
+impl From<RequestError<WireError>> for DriverError {
+    fn from(e: RequestError<WireError>) -> Self {
+        match e {
+            RequestError::Host(e) => e.into(),
+            RequestError::Timeout => DriverError::Transport(TransportError::Timeout),
+        }
+    }
+}
+
+/// How the host talks to the board, which depends on the firmware it was flashed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// The combined `aoc` firmware.
+    Solve,
+    /// The single-day `dayN` firmware.
+    Native,
+}
+
+/// How `run` talks to the board.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Most requests to keep in flight. 1 waits for every response before sending the next request.
+    pub window: usize,
+    /// Where to forward the `Progress` the board publishes. Only the combined firmware publishes it, and updates are
+    /// dropped while the receiver lags behind.
+    pub progress: Option<mpsc::Sender<Progress>>,
+    /// Where to forward the records the board logs. Records are dropped while the receiver lags behind.
+    pub logs: Option<mpsc::Sender<LogRecord>>,
+    /// Where to send the board's `Stats` for the run, if it serves them. Only the combined firmware does.
+    pub stats: Option<mpsc::Sender<Stats>>,
+    /// Where to send the round trip time of every line the host sends.
+    pub latency: Option<mpsc::UnboundedSender<Duration>>,
+    /// How long to wait for each response before taking the link to be gone.
+    pub timeout: Duration,
+    /// How many times to open the board again when the link to it is lost, before giving up.
+    pub reconnects: u32,
+    /// Where to send why the link was lost, each time the board is opened again.
+    pub link_lost: Option<mpsc::UnboundedSender<TransportError>>,
+}
+
+impl RunOptions {
+    /// A pipeline to the endpoint with the window, timeout and latency reporting asked for.
+    fn pipeline<'a, E>(&self, client: &'a HostClient<WireError>) -> Pipeline<'a, E, WireError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Schema + 'a,
+        E::Response: DeserializeOwned + Schema,
+    {
+        Pipeline::new(client, self.window).timeout(self.timeout).latency(self.latency.clone())
+    }
+
+    /// Sends a single request, waiting for its response no longer than the timeout.
+    async fn request<E>(&self, client: &HostClient<WireError>, req: &E::Request) -> Result<E::Response, DriverError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Schema,
+        E::Response: DeserializeOwned + Schema,
+    {
+        Ok(rpc::request::<E, _>(client, req, self.timeout).await?)
+    }
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            window: 8,
+            progress: None,
+            logs: None,
+            stats: None,
+            latency: None,
+            timeout: rpc::DEFAULT_TIMEOUT,
+            reconnects: 3,
+            link_lost: None,
+        }
+    }
+}
+
+/// How long to wait for a board that went away to come back, and how often to look for it meanwhile.
+const REOPEN_TIMEOUT: Duration = Duration::from_secs(10);
+const REOPEN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs `input` through the board at `target`, which runs `firmware` if it is emulated.
+///
+/// When the link is lost, the board is opened again, once it is back if it was unplugged. With the `Solve` protocol
+/// the run then goes on from the line the board got to, which may be past the last one it acknowledged, as requests
+/// that were in flight may have been taken. A board that lost power forgot the lines it was fed, though, and the
+/// single-day images start afresh on every connection, and another host may have reset the board in between, so
+/// otherwise the run starts over from the reset.
+pub async fn run(target: &Target, firmware: Firmware, protocol: Protocol, day: u8, input: &str, options: &RunOptions) -> Result<Answers, DriverError> {
+    // Checked up front, as the board would only fail the line once the lines before it are solved.
+    if let Some((i, line)) = input.lines().enumerate().find(|(_, line)| line.len() > LINE_SIZE) {
+        return Err(DriverError::LineTooLong { line: i + 1, len: line.len() });
+    }
+    let mut transport = AnyTransport::open(target, firmware).await.map_err(DriverError::Transport)?;
+    let mut resume = Resume::new();
+    let mut reconnects = 0;
+    loop {
+        let answers = match attempt(transport, protocol, day, input, options, &mut resume).await {
+            // The board answered `GetInfo` before, so not hearing back now is the link failing rather than old firmware.
+            Err(DriverError::Incompatible(Incompatibility::NoInfo)) if reconnects > 0 => Err(DriverError::Transport(TransportError::Timeout)),
+            answers => answers,
+        };
+        match answers {
+            Err(DriverError::Transport(e)) if e.is_link_lost() && reconnects < options.reconnects => {
+                reconnects += 1;
+                if let Some(link_lost) = &options.link_lost {
+                    link_lost.send(e).ok();
+                }
+                transport = reopen(target, firmware).await?;
+            }
+            answers => return answers,
+        }
+    }
+}
+
+/// Opens the board again, giving it time to come back after a reset or being plugged in again.
+async fn reopen(target: &Target, firmware: Firmware) -> Result<AnyTransport, DriverError> {
+    let deadline = Instant::now() + REOPEN_TIMEOUT;
+    loop {
+        tokio::time::sleep(REOPEN_INTERVAL).await;
+        match AnyTransport::open(target, firmware).await {
+            Ok(transport) => return Ok(transport),
+            Err(e) if Instant::now() >= deadline => return Err(DriverError::Transport(e)),
+            Err(_) => (),
+        }
+    }
+}
+
+/// How far a run got, so that once the board is opened again the run can be picked up where the board left off.
+#[derive(Debug)]
+struct Resume {
+    /// The token the run resets the board under, to tell its lines from those of a run that reset the board since.
+    session: u32,
+    /// Lines sent to the board since the reset.
+    sent: usize,
+    /// Lines the board acknowledged since the reset.
+    acked: usize,
+}
+
+impl Resume {
+    fn new() -> Self {
+        Self {
+            // Keyed at random for each run, which is all a token needs.
+            session: RandomState::new().build_hasher().finish() as u32,
+            sent: 0,
+            acked: 0,
+        }
+    }
+}
+
+/// Runs `input` through the board once, on a link that is given up when it fails.
+async fn attempt(transport: AnyTransport, protocol: Protocol, day: u8, input: &str, options: &RunOptions, resume: &mut Resume) -> Result<Answers, DriverError> {
+    let (client, mut worker) = rpc::new_client(transport, ERROR_PATH, options.window.max(8));
+    let answers = tokio::select! {
+        answers = drive(&client, protocol, day, input, options, resume) => answers,
+        // The worker only stops early when the link fails, and the requests in flight would wait out their timeout.
+        worker = &mut worker => return Err(DriverError::Transport(worker.ok().and_then(Result::err).unwrap_or(TransportError::Closed))),
+    };
+    // Wait for the transport to be closed, so the board can be opened again right away.
+    drop(client);
+    match (answers, worker.await) {
+        // The requests only saw the board go quiet, the worker knows why.
+        (Err(DriverError::Transport(TransportError::Closed | TransportError::Timeout)), Ok(Err(e))) => Err(DriverError::Transport(e)),
+        (answers, _) => answers,
+    }
+}
+
+async fn drive(client: &HostClient<WireError>, protocol: Protocol, day: u8, input: &str, options: &RunOptions, resume: &mut Resume) -> Result<Answers, DriverError> {
+    connect(client, options).await?;
+    match (protocol, day) {
+        (Protocol::Solve, _) => solve::run(client, day, input, options, resume).await,
+        (Protocol::Native, 1) => day1::run(client, input, options).await,
+        (Protocol::Native, 2) => day2::run(client, input, options).await,
+        (Protocol::Native, 3) => day3::run(client, input, options).await,
+        (Protocol::Native, _) => Err(DriverError::UnknownDay(day)),
+    }
+}
+
+/// Forwards the topics `options` asks for.
+async fn connect(client: &HostClient<WireError>, options: &RunOptions) -> Result<(), DriverError> {
+    if let Some(progress) = &options.progress {
+        forward::<ProgressTopic>(client, progress.clone()).await?;
+    }
+    if let Some(logs) = &options.logs {
+        forward::<LogTopic>(client, logs.clone()).await?;
+    }
+    Ok(())
+}
+
+/// Subscribes to a topic the board publishes and forwards it until the client goes away. Messages are dropped rather
+/// than waited for, since a full subscription would hold up the responses as well.
+async fn forward<T>(client: &HostClient<WireError>, tx: mpsc::Sender<T::Message>) -> Result<(), DriverError>
+where
+    T: Topic,
+    T::Message: DeserializeOwned + Send + 'static,
+{
+    let mut subscription = client.subscribe::<T>(8).await.map_err(|_| DriverError::Transport(TransportError::Closed))?;
+    tokio::spawn(async move {
+        while let Some(msg) = subscription.recv().await {
+            if let Err(TrySendError::Closed(_)) = tx.try_send(msg) {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// How long to wait for `GetInfo` before deciding that the board doesn't serve it. Firmware from before `GetInfo`
+/// silently drops requests it doesn't know.
+const INFO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Asks the board what it runs, and makes sure it serves `endpoints` with the same message types as the host.
+pub async fn check_compatible(client: &HostClient<WireError>, endpoints: &[EndpointKeys]) -> Result<Info, DriverError> {
+    let info = match tokio::time::timeout(INFO_TIMEOUT, client.send_resp::<GetInfo>(&())).await {
+        Ok(Ok(info)) => info,
+        Ok(Err(HostErr::Wire(WireError::UnknownEndpoint))) | Err(_) => return Err(DriverError::Incompatible(Incompatibility::NoInfo)),
+        Ok(Err(e)) => return Err(e.into()),
+    };
+    if info.protocol_version != PROTOCOL_VERSION {
+        let (board, host) = (info.protocol_version, PROTOCOL_VERSION);
+        return Err(DriverError::Incompatible(Incompatibility::ProtocolVersion { board, host }));
+    }
+    for keys in endpoints {
+        match info.endpoints.iter().find(|k| k.path == keys.path) {
+            None => return Err(DriverError::Incompatible(Incompatibility::MissingEndpoint(keys.path.to_string()))),
+            Some(k) if k != keys => return Err(DriverError::Incompatible(Incompatibility::SchemaMismatch(keys.path.to_string()))),
+            Some(_) => (),
+        }
+    }
+    Ok(info)
+}
+
+/// Asks the board what it runs.
+pub async fn info(transport: impl Transport) -> Result<Info, DriverError> {
+    let (client, worker) = rpc::new_client(transport, ERROR_PATH, 8);
+    let info = check_compatible(&client, &[]).await;
+    drop(client);
+    worker.await.ok();
+    info
+}
+
+/// Every endpoint the host knows how to talk to.
+pub fn known_endpoints() -> Vec<EndpointKeys> {
+    vec![
+        EndpointKeys::of::<GetInfo>(),
+        EndpointKeys::of::<ListDays>(),
+        EndpointKeys::of::<GetStats>(),
+        EndpointKeys::of::<icd::day1::Solve>(),
+        EndpointKeys::of::<icd::day1::Calibration>(),
+        EndpointKeys::of::<icd::day2::Solve>(),
+        EndpointKeys::of::<icd::day2::Cubes>(),
+        EndpointKeys::of::<icd::day3::Solve>(),
+        EndpointKeys::of::<icd::day3::Engine>(),
+        EndpointKeys::of::<icd::day3::GetResult>(),
+    ]
+}
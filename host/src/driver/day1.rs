@@ -0,0 +1,32 @@
+use aoc_2023_icd::chunk::chunks;
+use aoc_2023_icd::day1::{Calibration, ClientToHost, HostToClient, CHUNK_SIZE};
+use aoc_2023_icd::error::WireError;
+use aoc_2023_icd::info::EndpointKeys;
+use aoc_2023_icd::solver::Answers;
+use postcard_rpc::host_client::HostClient;
+
+use super::{DriverError, RunOptions};
+
+pub async fn run(client: &HostClient<WireError>, input: &str, options: &RunOptions) -> Result<Answers, DriverError> {
+    super::check_compatible(client, &[EndpointKeys::of::<Calibration>()]).await?;
+
+    let mut pipeline = options.pipeline::<Calibration>(client);
+    pipeline.send(HostToClient::Reset).await?;
+    for (i, line) in input.lines().enumerate() {
+        for chunk in chunks::<CHUNK_SIZE>(i as u32, line) {
+            pipeline.send(HostToClient::Data(chunk)).await?;
+        }
+    }
+    pipeline.flush().await?;
+
+    let a = result(options.request::<Calibration>(client, &HostToClient::GetResultA).await?)?;
+    let b = result(options.request::<Calibration>(client, &HostToClient::GetResultB).await?)?;
+    Ok(Answers { a, b: Some(b) })
+}
+
+fn result(resp: ClientToHost) -> Result<u32, DriverError> {
+    match resp {
+        ClientToHost::Result(sum) => Ok(sum),
+        ClientToHost::Ack => Err(DriverError::Protocol),
+    }
+}
@@ -0,0 +1,83 @@
+use std::iter::Enumerate;
+use std::str::Lines;
+
+use aoc_2023_icd::chunk::chunks;
+use aoc_2023_icd::day2::{process, ClientToHost, Cubes, HostToClient, StateMachine, CHUNK_SIZE};
+use aoc_2023_icd::error::WireError;
+use aoc_2023_icd::info::EndpointKeys;
+use aoc_2023_icd::solver::Answers;
+use postcard_rpc::host_client::HostClient;
+
+use super::{DriverError, RunOptions};
+use crate::rpc::Pipeline;
+
+pub async fn run(client: &HostClient<WireError>, input: &str, options: &RunOptions) -> Result<Answers, DriverError> {
+    super::check_compatible(client, &[EndpointKeys::of::<Cubes>()]).await?;
+    let mut host = Host {
+        client,
+        options,
+        pipeline: options.pipeline(client),
+        lines: input.lines().enumerate(),
+        error: None,
+    };
+    let result = process(&mut host).await;
+    if let Some(e) = host.error {
+        return Err(e);
+    }
+    let (a, b) = result.map_err(|_| DriverError::Protocol)?;
+    Ok(Answers { a, b: Some(b) })
+}
+
+struct Host<'a> {
+    client: &'a HostClient<WireError>,
+    options: &'a RunOptions,
+    /// Carries the games, which are only acknowledged, so they don't have to wait for each other.
+    pipeline: Pipeline<'a, Cubes, WireError>,
+    lines: Enumerate<Lines<'a>>,
+    /// Why a request failed, since `process` takes any failure writing a game to mean that all games have been
+    /// written.
+    error: Option<DriverError>,
+}
+
+impl Host<'_> {
+    async fn request(&mut self, message: HostToClient) -> Result<ClientToHost, ()> {
+        self.options.request::<Cubes>(self.client, &message).await.map_err(|e| self.error = Some(e))
+    }
+
+    /// Fails unless every response is a `GameDataWritten`.
+    fn check_written(&mut self, responses: impl IntoIterator<Item = ClientToHost>) -> Result<(), ()> {
+        if responses.into_iter().all(|resp| resp == ClientToHost::GameDataWritten) {
+            Ok(())
+        } else {
+            self.error = Some(DriverError::Protocol);
+            Err(())
+        }
+    }
+}
+
+impl StateMachine for Host<'_> {
+    async fn start(&mut self) -> Result<(), ()> {
+        match self.request(HostToClient::Start).await? {
+            ClientToHost::Started => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    async fn write_next_game(&mut self) -> Result<(), ()> {
+        let (i, line) = self.lines.next().ok_or(())?;
+        for chunk in chunks::<CHUNK_SIZE>(i as u32, line) {
+            let oldest = self.pipeline.send(HostToClient::GameData(chunk)).await.map_err(|e| self.error = Some(e.into()))?;
+            self.check_written(oldest)?;
+        }
+        Ok(())
+    }
+
+    async fn end(&mut self) -> Result<(u32, u32), ()> {
+        let responses = self.pipeline.flush().await.map_err(|e| self.error = Some(e.into()))?;
+        self.check_written(responses)?;
+        match self.request(HostToClient::GetResult).await? {
+            ClientToHost::Result(res) => Ok(res),
+            _ => Err(()),
+        }
+    }
+}
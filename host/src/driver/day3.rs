@@ -0,0 +1,22 @@
+use aoc_2023_icd::chunk::chunks;
+use aoc_2023_icd::day3::{Engine, EngineReq, GetResult, CHUNK_SIZE};
+use aoc_2023_icd::error::WireError;
+use aoc_2023_icd::info::EndpointKeys;
+use aoc_2023_icd::solver::Answers;
+use postcard_rpc::host_client::HostClient;
+
+use super::{DriverError, RunOptions};
+
+pub async fn run(client: &HostClient<WireError>, input: &str, options: &RunOptions) -> Result<Answers, DriverError> {
+    super::check_compatible(client, &[EndpointKeys::of::<Engine>(), EndpointKeys::of::<GetResult>()]).await?;
+
+    let mut pipeline = options.pipeline::<Engine>(client);
+    pipeline.send(EngineReq::Reset).await?;
+    for (i, line) in input.lines().enumerate() {
+        for chunk in chunks::<CHUNK_SIZE>(i as u32, line) {
+            pipeline.send(EngineReq::Data(chunk)).await?;
+        }
+    }
+    pipeline.flush().await?;
+    options.request::<GetResult>(client, &()).await
+}
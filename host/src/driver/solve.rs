@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use aoc_2023_icd::chunk::chunks;
+use aoc_2023_icd::error::WireError;
+use aoc_2023_icd::info::EndpointKeys;
+use aoc_2023_icd::solver::{batch_capacity, Answers, SolveReq, SolveResp, BATCH_SIZE, CHUNK_SIZE};
+use aoc_2023_icd::stats::GetStats;
+use aoc_2023_icd::{day1, day2, day3};
+use heapless::String;
+use postcard_rpc::host_client::HostClient;
+use postcard_rpc::Endpoint;
+
+use super::{DriverError, Resume, RunOptions};
+use crate::rpc::Pipeline;
+
+/// Picks the run up where `resume` says the board left off, if it still holds those lines.
+pub(super) async fn run(client: &HostClient<WireError>, day: u8, input: &str, options: &RunOptions, resume: &mut Resume) -> Result<Answers, DriverError> {
+    match day {
+        1 => solve::<day1::Solve>(client, input, options, resume).await,
+        2 => solve::<day2::Solve>(client, input, options, resume).await,
+        3 => solve::<day3::Solve>(client, input, options, resume).await,
+        _ => Err(DriverError::UnknownDay(day)),
+    }
+}
+
+async fn solve<E>(client: &HostClient<WireError>, input: &str, options: &RunOptions, resume: &mut Resume) -> Result<Answers, DriverError>
+where
+    E: Endpoint<Request = SolveReq, Response = SolveResp>,
+{
+    let info = super::check_compatible(client, &[EndpointKeys::of::<E>()]).await?;
+    let capacity = batch_capacity(info.read_size as usize);
+    // The board counts from boot, so the run's share is what changed in between.
+    let stats = match &options.stats {
+        Some(tx) if info.endpoints.contains(&EndpointKeys::of::<GetStats>()) => Some((tx, options.request::<GetStats>(client, &()).await?)),
+        _ => None,
+    };
+    let from = resume_from::<E>(client, options, resume).await?;
+    let session = resume.session;
+    let mut pipeline = Feed::new(options.pipeline::<E>(client), resume);
+    if from == 0 {
+        pipeline.send(SolveReq::Reset(session)).await?;
+    }
+    let mut batch: Option<String<BATCH_SIZE>> = None;
+    for (i, line) in input.lines().enumerate().skip(from) {
+        if line.len() > capacity {
+            if let Some(batch) = batch.take() {
+                pipeline.send(SolveReq::Batch(batch)).await?;
+            }
+            for chunk in chunks::<CHUNK_SIZE>(i as u32, line) {
+                pipeline.send(SolveReq::Line(chunk)).await?;
+            }
+            continue;
+        }
+        if batch.as_ref().is_some_and(|b| b.len() + 1 + line.len() > capacity) {
+            if let Some(full) = batch.take() {
+                pipeline.send(SolveReq::Batch(full)).await?;
+            }
+        }
+        match &mut batch {
+            Some(batch) => {
+                // Both pushes fit, as checked above.
+                batch.push('\n').ok();
+                batch.push_str(line).ok();
+            }
+            None => batch = String::from_str(line).ok(),
+        }
+    }
+    if let Some(batch) = batch {
+        pipeline.send(SolveReq::Batch(batch)).await?;
+    }
+    pipeline.flush().await?;
+    let answers = match options.request::<E>(client, &SolveReq::Finish).await? {
+        SolveResp::Answers(answers) => answers,
+        SolveResp::Ack | SolveResp::Lines { .. } => return Err(DriverError::Protocol),
+    };
+    if let Some((tx, before)) = stats {
+        let after = options.request::<GetStats>(client, &()).await?;
+        tx.try_send(after.since(&before)).ok();
+    }
+    Ok(answers)
+}
+
+/// Where to pick the run up after losing the link: the line the board got to, if it is still in the run's session and
+/// that is at least as far as it acknowledged and no further than it was sent. Otherwise, as on the first attempt, the
+/// run starts over from a reset.
+async fn resume_from<E>(client: &HostClient<WireError>, options: &RunOptions, resume: &mut Resume) -> Result<usize, DriverError>
+where
+    E: Endpoint<Request = SolveReq, Response = SolveResp>,
+{
+    let from = match resume.sent {
+        0 => 0,
+        _ => match options.request::<E>(client, &SolveReq::Lines).await? {
+            SolveResp::Lines { session, lines } if session == resume.session && (resume.acked..=resume.sent).contains(&(lines as usize)) => lines as usize,
+            SolveResp::Lines { .. } => 0,
+            SolveResp::Ack | SolveResp::Answers(_) => return Err(DriverError::Protocol),
+        },
+    };
+    resume.sent = from;
+    resume.acked = from;
+    Ok(from)
+}
+
+/// A pipeline that keeps count of the lines in the requests sent through it, and of those the board acknowledged.
+struct Feed<'a, 'r, E: Endpoint> {
+    pipeline: Pipeline<'a, E, WireError>,
+    /// How many lines each request still in flight completes, oldest first.
+    in_flight: VecDeque<usize>,
+    resume: &'r mut Resume,
+}
+
+impl<'a, 'r, E> Feed<'a, 'r, E>
+where
+    E: Endpoint<Request = SolveReq, Response = SolveResp>,
+{
+    fn new(pipeline: Pipeline<'a, E, WireError>, resume: &'r mut Resume) -> Self {
+        Self {
+            pipeline,
+            in_flight: VecDeque::new(),
+            resume,
+        }
+    }
+
+    async fn send(&mut self, req: SolveReq) -> Result<(), DriverError> {
+        let lines = match &req {
+            SolveReq::Line(chunk) => usize::from(chunk.last),
+            SolveReq::Batch(lines) => lines.split('\n').count(),
+            SolveReq::Reset(_) | SolveReq::Finish | SolveReq::Lines => 0,
+        };
+        self.resume.sent += lines;
+        self.in_flight.push_back(lines);
+        if self.pipeline.send(req).await?.is_some() {
+            self.acked(1);
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), DriverError> {
+        let responses = self.pipeline.flush().await?;
+        self.acked(responses.len());
+        Ok(())
+    }
+
+    fn acked(&mut self, responses: usize) {
+        self.resume.acked += self.in_flight.drain(..responses).sum::<usize>();
+    }
+}
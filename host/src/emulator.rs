@@ -0,0 +1,139 @@
+//! An in-process stand-in for the board, so the host tools can be run without one.
+//!
+//! The emulated device runs the same `aoc_2023_server` dispatchers and server loop as the firmware, on an in-memory byte
+//! stream standing in for the USB link, or on any other stream like a pseudo-terminal.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::io;
+use std::time::Instant;
+
+use aoc_2023_icd::log::{Level, LogRecord};
+use aoc_2023_server::images::{Aoc, BoardServer, Build, Day1, Day2, Day3, Platform, READ_SIZE, WRITE_SIZE};
+use aoc_2023_server::repl::Repl;
+use aoc_2023_server::rpc::{Dispatch, Server};
+use embedded_io_adapters::tokio_1::FromTokio;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_serial::{SerialPort, SerialStream};
+
+use crate::transport::DuplexTransport;
+
+/// Which firmware image the emulated board runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Firmware {
+    /// The combined `aoc` image serving every day.
+    Aoc,
+    /// The single-day `dayN` image.
+    Day(u8),
+}
+
+/// Spawns a device task running the given firmware, like flashing the board with it, and returns the host end of its
+/// link.
+pub fn spawn(firmware: Firmware) -> DuplexTransport {
+    let (host, device) = tokio::io::duplex(WRITE_SIZE);
+    tokio::spawn(serve_stream(firmware, device));
+    DuplexTransport::new(host)
+}
+
+/// Serves a board running the given firmware on a byte stream, like the firmware does on its USB functions, until
+/// either side hangs up.
+pub async fn serve_stream(firmware: Firmware, stream: impl AsyncRead + AsyncWrite + Unpin) {
+    let mut server = Server::new(FromTokio::new(stream));
+    match firmware {
+        Firmware::Aoc => server.serve(&mut Aoc::new(Emulated::new())).await,
+        Firmware::Day(1) => server.serve(&mut Day1::new(Emulated::new())).await,
+        Firmware::Day(2) => server.serve(&mut Day2::new(Emulated::new())).await,
+        Firmware::Day(3) => server.serve(&mut Day3::new(Emulated::new())).await,
+        Firmware::Day(day) => panic!("no emulated firmware for day {day}"),
+    };
+}
+
+/// Serves one board running the given firmware to the connections to `listener`, one at a time, like the firmware does
+/// on its TCP port. The board keeps what it was fed from one connection to the next, though the single-day images
+/// start afresh on every connection, as they do on USB.
+pub async fn serve_tcp(firmware: Firmware, listener: TcpListener) -> io::Result<()> {
+    match firmware {
+        Firmware::Aoc => serve_connections(&listener, &mut Aoc::new(Emulated::new()), |_| ()).await,
+        Firmware::Day(1) => serve_connections(&listener, &mut Day1::new(Emulated::new()), Day1::reset).await,
+        Firmware::Day(2) => serve_connections(&listener, &mut Day2::new(Emulated::new()), Day2::reset).await,
+        Firmware::Day(3) => serve_connections(&listener, &mut Day3::new(Emulated::new()), Day3::reset).await,
+        Firmware::Day(day) => panic!("no emulated firmware for day {day}"),
+    }
+}
+
+async fn serve_connections<D>(listener: &TcpListener, image: &mut D, reset: impl Fn(&mut D)) -> io::Result<()>
+where
+    D: Dispatch<READ_SIZE, WRITE_SIZE>,
+{
+    loop {
+        let (stream, _) = listener.accept().await?;
+        stream.set_nodelay(true)?;
+        reset(image);
+        BoardServer::new(FromTokio::new(stream)).serve(image).await;
+    }
+}
+
+/// Serves the text console of the `aoc` firmware on a byte stream until it ends, like the firmware does on its second
+/// CDC-ACM port.
+pub async fn serve_console(stream: impl AsyncRead + AsyncWrite + Unpin) -> io::Result<()> {
+    let start = Instant::now();
+    Repl::new().run(&mut FromTokio::new(stream), || start.elapsed().as_micros() as u64).await
+}
+
+/// Opens a pseudo-terminal for `serve_stream` or `serve_console`, returning its controlling end and the path of the
+/// other end.
+///
+/// The other end is kept open for good, so the controlling end doesn't hang up whenever the host closes it.
+pub fn open_pty() -> io::Result<(SerialStream, String)> {
+    let (port, other) = SerialStream::pair()?;
+    let path = other.name().ok_or_else(|| io::Error::other("the pseudo-terminal has no name"))?;
+    std::mem::forget(other);
+    Ok((port, path))
+}
+
+/// Stands in for the board's clock, log and build, started when the board is emulated.
+struct Emulated {
+    start: Instant,
+    records: VecDeque<LogRecord>,
+}
+
+impl Emulated {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl Platform for Emulated {
+    fn build(&self) -> Build {
+        Build {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: "emulator",
+            profile: if cfg!(debug_assertions) { "debug" } else { "release" },
+        }
+    }
+
+    fn now_us(&self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
+
+    fn log(&mut self, level: Level, args: fmt::Arguments) {
+        let mut message = heapless::String::new();
+        // A message that doesn't fit is cut short, like on the board.
+        let _ = message.write_fmt(args);
+        let timestamp_ms = self.start.elapsed().as_millis() as u32;
+        self.records.push_back(LogRecord { level, timestamp_ms, message });
+    }
+
+    fn pop_log(&mut self) -> Option<LogRecord> {
+        self.records.pop_front()
+    }
+
+    /// The emulator's stack has nothing to do with the board's.
+    fn peak_stack(&self) -> Option<u32> {
+        None
+    }
+}
@@ -1 +1,6 @@
-pub mod rpc;
+pub mod bench;
+pub mod driver;
+pub mod emulator;
+pub mod rpc;
+pub mod transport;
+pub mod verify;
@@ -1,49 +1,152 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-use nusb::transfer::{Completion, RequestBuffer};
-use nusb::{Device, Interface};
+use aoc_2023_server::images::WRITE_SIZE;
+use futures::future::LocalBoxFuture;
+use futures::stream::{FuturesOrdered, StreamExt};
 use postcard::experimental::schema::Schema;
 use postcard_rpc::accumulator::raw::{CobsAccumulator, FeedResult};
 use postcard_rpc::headered::extract_header_from_bytes;
-use postcard_rpc::host_client::{HostClient, ProcessError, RpcFrame, WireContext};
-use postcard_rpc::Key;
+use postcard_rpc::host_client::{HostClient, HostErr, ProcessError, RpcFrame, WireContext};
+use postcard_rpc::{Endpoint, Key};
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use tokio::select;
-use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::{Sender, UnboundedSender};
+use tokio::task::JoinHandle;
 
-pub fn new_client<E: DeserializeOwned + Schema>(device: Device, err_uri_path: &str, outgoing_depth: usize) -> HostClient<E> {
-    let mut comm = UsbComm::new(device);
+use crate::transport::{Transport, TransportError};
+
+/// Spawns the wire worker for a new client talking over the given transport. The worker closes the transport and
+/// ends once every clone of the client is dropped, or returns why the transport failed. Requests still waiting for
+/// their responses then only end when they time out.
+///
+/// Run the client on a current-thread runtime: `HostClient::send_resp` only starts waiting for the response after the
+/// request has been queued, and a fast device (like the emulator) can answer before that on a multi-threaded runtime,
+/// in which case the response is dropped.
+pub fn new_client<E: DeserializeOwned + Schema>(transport: impl Transport, err_uri_path: &str, outgoing_depth: usize) -> (HostClient<E>, JoinHandle<Result<(), TransportError>>) {
+    let mut comm = WireComm::new(transport);
     let (client, wire) = HostClient::<E>::new_manual(err_uri_path, outgoing_depth);
-    tokio::task::spawn(async move { comm.wire_worker(wire).await });
-    client
+    let worker = tokio::task::spawn(async move { comm.wire_worker(wire).await });
+    (client, worker)
 }
 
-struct UsbComm {
-    interface: Interface,
+/// How long to wait for a response unless told otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum RequestError<WireErr> {
+    Host(HostErr<WireErr>),
+    /// No response within the timeout, because the board is stuck or the link is gone.
+    Timeout,
 }
 
-impl UsbComm {
-    pub fn new(device: Device) -> Self {
+impl<WireErr> From<HostErr<WireErr>> for RequestError<WireErr> {
+    fn from(e: HostErr<WireErr>) -> Self {
+        RequestError::Host(e)
+    }
+}
+
+/// Sends a request and waits at most `timeout` for the response, since `HostClient` would wait forever for a board
+/// that went away.
+pub async fn request<E, WireErr>(client: &HostClient<WireErr>, req: &E::Request, timeout: Duration) -> Result<E::Response, RequestError<WireErr>>
+where
+    E: Endpoint,
+    E::Request: Serialize + Schema,
+    E::Response: DeserializeOwned + Schema,
+    WireErr: DeserializeOwned + Schema,
+{
+    match tokio::time::timeout(timeout, client.send_resp::<E>(req)).await {
+        Ok(resp) => Ok(resp?),
+        Err(_) => Err(RequestError::Timeout),
+    }
+}
+
+/// Keeps up to `window` requests to one endpoint in flight, so throughput isn't bound by the round trip to the board.
+/// `HostClient` matches the responses to the requests by sequence number; they are returned in the order the requests
+/// were sent.
+pub struct Pipeline<'a, E: Endpoint, WireErr> {
+    client: &'a HostClient<WireErr>,
+    window: usize,
+    timeout: Duration,
+    latency: Option<UnboundedSender<Duration>>,
+    in_flight: FuturesOrdered<LocalBoxFuture<'a, Result<E::Response, RequestError<WireErr>>>>,
+}
+
+impl<'a, E, WireErr> Pipeline<'a, E, WireErr>
+where
+    E: Endpoint,
+    E::Request: Serialize + Schema + 'a,
+    E::Response: DeserializeOwned + Schema,
+    WireErr: DeserializeOwned + Schema,
+{
+    /// A window of 1 waits for every response before sending the next request.
+    pub fn new(client: &'a HostClient<WireErr>, window: usize) -> Self {
         Self {
-            interface: device.claim_interface(0).unwrap(),
+            client,
+            window: window.max(1),
+            timeout: DEFAULT_TIMEOUT,
+            latency: None,
+            in_flight: FuturesOrdered::new(),
         }
     }
 
-    pub async fn write(&mut self, data: &[u8]) -> Result<(), ()> {
-        let _ = self.interface.bulk_out(0x01, data.into()).await;
-        Ok(())
+    /// Fails requests that aren't answered within `timeout`, counted from when each was queued.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Sends the round trip time of every request to `latency`, from queueing the request to getting the response.
+    pub fn latency(self, latency: Option<UnboundedSender<Duration>>) -> Self {
+        Self { latency, ..self }
+    }
+
+    /// Queues the request. If the window is full, first waits for the oldest request and returns its response.
+    pub async fn send(&mut self, req: E::Request) -> Result<Option<E::Response>, RequestError<WireErr>> {
+        let oldest = if self.in_flight.len() >= self.window { self.in_flight.next().await.transpose()? } else { None };
+        let (client, timeout) = (self.client, self.timeout);
+        let latency = self.latency.clone();
+        // Taken now, as the future doesn't start until the window is waited on.
+        let start = Instant::now();
+        self.in_flight.push_back(Box::pin(async move {
+            let resp = request::<E, _>(client, &req, timeout).await;
+            if let Some(latency) = latency {
+                latency.send(start.elapsed()).ok();
+            }
+            resp
+        }));
+        Ok(oldest)
     }
 
-    pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
-        if let Completion { data, status: Ok(()) } = self.interface.bulk_in(0x81, RequestBuffer::new(4096)).await {
-            buf[..data.len()].copy_from_slice(&data);
-            return Ok(data.len());
+    /// Waits for every request still in flight and returns their responses.
+    pub async fn flush(&mut self) -> Result<Vec<E::Response>, RequestError<WireErr>> {
+        let mut responses = Vec::with_capacity(self.in_flight.len());
+        while let Some(resp) = self.in_flight.next().await {
+            responses.push(resp?);
         }
-        Err(())
+        Ok(responses)
+    }
+}
+
+struct WireComm<T> {
+    transport: T,
+}
+
+impl<T: Transport> WireComm<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
     }
-    async fn wire_worker(&mut self, ctx: WireContext) {
-        let mut buf = [0u8; 1024];
-        let mut acc = CobsAccumulator::<1024>::new();
+
+    pub async fn write(&mut self, data: Vec<u8>) -> Result<(), TransportError> {
+        self.transport.send(data).await
+    }
+
+    pub async fn read(&mut self) -> Result<Vec<u8>, TransportError> {
+        self.transport.receive().await
+    }
+    async fn wire_worker(&mut self, ctx: WireContext) -> Result<(), TransportError> {
+        // Every firmware image sends frames of up to `WRITE_SIZE`, so the largest of them fits.
+        let mut acc = CobsAccumulator::<WRITE_SIZE>::new();
         let mut subs: HashMap<Key, Sender<RpcFrame>> = HashMap::new();
 
         let WireContext { mut outgoing, incoming, mut new_subs } = ctx;
@@ -53,7 +156,7 @@ impl UsbComm {
             select! {
                 sub = new_subs.recv() => {
                     let Some(si) = sub else {
-                        return;
+                        return Ok(());
                     };
 
                     subs.insert(si.key, si.tx);
@@ -61,7 +164,7 @@ impl UsbComm {
                 out = outgoing.recv() => {
                     // Receiver returns None when all Senders have hung up
                     let Some(msg) = out else {
-                        return;
+                        return Ok(());
                     };
 
                     // Turn the serialized message into a COBS encoded message
@@ -75,17 +178,12 @@ impl UsbComm {
 
 
                     // And send it!
-                    if self.write(&msg).await.is_err() {
-                        // I guess the serial port hung up.
-                        return;
-                    }
+                    self.write(msg).await?;
                 }
-                inc = self.read(&mut buf) => {
+                inc = self.read() => {
                     // if read errored, we're done
-                    let Ok(used) = inc else {
-                        return;
-                    };
-                    let mut window = &buf[..used];
+                    let data = inc?;
+                    let mut window = &data[..];
 
                     'cobs: while !window.is_empty() {
                         window = match acc.feed(window) {
@@ -113,7 +211,7 @@ impl UsbComm {
                                     } else {
                                         // Wake the given sequence number. If the WaitMap is closed, we're done here
                                         if let Err(ProcessError::Closed) = incoming.process(frame) {
-                                            return;
+                                            return Ok(());
                                         }
                                     }
                                 }
@@ -0,0 +1,106 @@
+//! The ways the host can reach a board.
+//!
+//! A transport only moves chunks of bytes: one bulk transfer on USB, or whatever a single read returned on a byte
+//! stream. Message boundaries are up to the protocol on top, which is why everything but Day 1 is COBS framed.
+
+use std::future::Future;
+use std::io;
+
+use nusb::transfer::TransferError;
+
+use crate::emulator::{self, Firmware};
+
+pub mod stream;
+pub mod usb;
+
+pub use stream::{DuplexTransport, SerialTransport, TcpTransport};
+pub use usb::UsbTransport;
+
+#[derive(Debug)]
+pub enum TransportError {
+    NotFound,
+    Usb(nusb::Error),
+    Transfer(TransferError),
+    Io(io::Error),
+    Closed,
+    /// The board was unplugged, or reset itself.
+    Disconnected,
+    /// The board didn't answer a request in time.
+    Timeout,
+    /// The platform doesn't report which USB interface a serial port belongs to, so the board's protocol port can't be
+    /// told from its console.
+    NoInterfaceNumber,
+}
+
+impl TransportError {
+    /// Whether the link to the board is gone, so that the board has to be opened again to go on.
+    pub fn is_link_lost(&self) -> bool {
+        match self {
+            TransportError::Transfer(_) | TransportError::Io(_) | TransportError::Closed | TransportError::Disconnected | TransportError::Timeout => true,
+            TransportError::NotFound | TransportError::Usb(_) | TransportError::NoInterfaceNumber => false,
+        }
+    }
+}
+
+pub trait Transport: Send + 'static {
+    fn send(&mut self, data: Vec<u8>) -> impl Future<Output = Result<(), TransportError>> + Send;
+    fn receive(&mut self) -> impl Future<Output = Result<Vec<u8>, TransportError>> + Send;
+}
+
+/// Any of the transports, picked at runtime.
+pub enum AnyTransport {
+    Usb(UsbTransport),
+    Duplex(DuplexTransport),
+    Tcp(TcpTransport),
+    Serial(SerialTransport),
+}
+
+/// Where to find the board.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// The board with the given serial number, or the first one found.
+    Usb {
+        serial_number: Option<String>,
+    },
+    /// A board emulated in-process.
+    Emulator,
+    Tcp(String),
+    Serial(String),
+    /// The CDC-ACM serial port of the board with the given serial number, or of the first one found.
+    Acm {
+        serial_number: Option<String>,
+    },
+}
+
+impl AnyTransport {
+    /// Connects to the target. `firmware` is what an emulated board runs; a real one runs whatever it was flashed with.
+    pub async fn open(target: &Target, firmware: Firmware) -> Result<Self, TransportError> {
+        Ok(match target {
+            Target::Usb { serial_number } => AnyTransport::Usb(UsbTransport::open(serial_number.as_deref())?),
+            Target::Emulator => AnyTransport::Duplex(emulator::spawn(firmware)),
+            Target::Tcp(addr) => AnyTransport::Tcp(TcpTransport::connect(addr).await?),
+            Target::Serial(path) => AnyTransport::Serial(SerialTransport::open(path)?),
+            Target::Acm { serial_number } => AnyTransport::Serial(SerialTransport::open(&SerialTransport::find(serial_number.as_deref())?)?),
+        })
+    }
+}
+
+impl Transport for AnyTransport {
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), TransportError> {
+        match self {
+            AnyTransport::Usb(t) => t.send(data).await,
+            AnyTransport::Duplex(t) => t.send(data).await,
+            AnyTransport::Tcp(t) => t.send(data).await,
+            AnyTransport::Serial(t) => t.send(data).await,
+        }
+    }
+
+    async fn receive(&mut self) -> Result<Vec<u8>, TransportError> {
+        match self {
+            AnyTransport::Usb(t) => t.receive().await,
+            AnyTransport::Duplex(t) => t.receive().await,
+            AnyTransport::Tcp(t) => t.receive().await,
+            AnyTransport::Serial(t) => t.receive().await,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::net::IpAddr;
+
+use aoc_2023_icd::{ACM_INTERFACE, PID, TCP_PORT, VID};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio::net::TcpStream;
+use tokio_serial::{SerialPortBuilderExt, SerialPortInfo, SerialPortType, SerialStream};
+
+use super::{Transport, TransportError};
+
+/// Baud rate for serial ports. Ignored by USB CDC-ACM and pseudo-terminals, but still has to be set.
+const BAUD: u32 = 115_200;
+
+/// A transport over any byte stream, like a TCP socket, a serial port or the in-memory link to an emulated board.
+pub struct StreamTransport<S> {
+    stream: S,
+}
+
+pub type TcpTransport = StreamTransport<TcpStream>;
+pub type SerialTransport = StreamTransport<SerialStream>;
+pub type DuplexTransport = StreamTransport<DuplexStream>;
+
+impl<S> StreamTransport<S> {
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+}
+
+impl TcpTransport {
+    /// Connects to `addr`, on the board's `TCP_PORT` unless it names a port.
+    pub async fn connect(addr: &str) -> Result<Self, TransportError> {
+        let stream = match addr.parse::<IpAddr>() {
+            Ok(ip) => TcpStream::connect((ip, TCP_PORT)).await,
+            Err(_) if !addr.contains(':') => TcpStream::connect((addr, TCP_PORT)).await,
+            Err(_) => TcpStream::connect(addr).await,
+        };
+        let stream = stream.map_err(TransportError::Io)?;
+        stream.set_nodelay(true).map_err(TransportError::Io)?;
+        Ok(Self::new(stream))
+    }
+}
+
+impl SerialTransport {
+    /// Opens a serial device like `/dev/ttyACM0`, or one end of a Unix pseudo-terminal.
+    pub fn open(path: &str) -> Result<Self, TransportError> {
+        let port = tokio_serial::new(path, BAUD).open_native_async().map_err(|e| TransportError::Io(e.into()))?;
+        Ok(Self::new(port))
+    }
+
+    /// Finds the CDC-ACM serial port of the board with the given serial number, or of the first board found.
+    pub fn find(serial_number: Option<&str>) -> Result<String, TransportError> {
+        let ports = tokio_serial::available_ports().map_err(|e| TransportError::Io(e.into()))?;
+        select(ports, serial_number)
+    }
+}
+
+/// Picks the board's protocol port out of `ports`, leaving out the console port that has the same VID, PID and serial
+/// number.
+fn select(ports: Vec<SerialPortInfo>, serial_number: Option<&str>) -> Result<String, TransportError> {
+    for port in ports {
+        let SerialPortType::UsbPort(usb) = &port.port_type else {
+            continue;
+        };
+        if usb.vid != VID || usb.pid != PID || serial_number.is_some_and(|s| usb.serial_number.as_deref() != Some(s)) {
+            continue;
+        }
+        // Linux and Windows report the function's communication interface, macOS the data interface after it.
+        match usb.interface {
+            Some(i) if i == ACM_INTERFACE || i == ACM_INTERFACE + 1 => return Ok(port.port_name),
+            Some(_) => (),
+            None => return Err(TransportError::NoInterfaceNumber),
+        }
+    }
+    Err(TransportError::NotFound)
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send + 'static> Transport for StreamTransport<S> {
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), TransportError> {
+        self.stream.write_all(&data).await.map_err(TransportError::Io)
+    }
+
+    async fn receive(&mut self) -> Result<Vec<u8>, TransportError> {
+        let mut buf = vec![0; 4096];
+        match self.stream.read(&mut buf).await.map_err(TransportError::Io)? {
+            0 => Err(TransportError::Closed),
+            n => {
+                buf.truncate(n);
+                Ok(buf)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_serial::UsbPortInfo;
+
+    use super::*;
+
+    fn port(name: &str, serial_number: &str, interface: Option<u8>) -> SerialPortInfo {
+        SerialPortInfo {
+            port_name: name.to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: VID,
+                pid: PID,
+                serial_number: Some(serial_number.to_string()),
+                manufacturer: None,
+                product: None,
+                interface,
+            }),
+        }
+    }
+
+    #[test]
+    fn console_port_is_passed_over() {
+        let ports = vec![port("/dev/ttyACM0", "A", Some(3)), port("/dev/ttyACM1", "A", Some(1))];
+        assert_eq!(select(ports, None).unwrap(), "/dev/ttyACM1");
+    }
+
+    #[test]
+    fn data_interface_is_taken_too() {
+        let ports = vec![port("/dev/cu.usbmodem1", "A", Some(4)), port("/dev/cu.usbmodem2", "A", Some(2))];
+        assert_eq!(select(ports, None).unwrap(), "/dev/cu.usbmodem2");
+    }
+
+    #[test]
+    fn serial_number_picks_the_board() {
+        let ports = vec![port("/dev/ttyACM0", "A", Some(1)), port("/dev/ttyACM2", "B", Some(1))];
+        assert_eq!(select(ports, Some("B")).unwrap(), "/dev/ttyACM2");
+        let ports = vec![port("/dev/ttyACM0", "A", Some(1))];
+        assert!(matches!(select(ports, Some("B")), Err(TransportError::NotFound)));
+    }
+
+    #[test]
+    fn missing_interface_number_is_an_error() {
+        let ports = vec![port("/dev/ttyACM0", "A", None), port("/dev/ttyACM1", "A", None)];
+        assert!(matches!(select(ports, None), Err(TransportError::NoInterfaceNumber)));
+    }
+}
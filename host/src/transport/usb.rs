@@ -0,0 +1,56 @@
+use aoc_2023_icd::{PID, VID};
+use nusb::transfer::{Completion, RequestBuffer, TransferError};
+use nusb::{DeviceInfo, Interface};
+
+use super::{Transport, TransportError};
+
+const BULK_OUT_EP: u8 = 0x01;
+const BULK_IN_EP: u8 = 0x81;
+
+/// The vendor-specific bulk endpoints of the board.
+pub struct UsbTransport {
+    interface: Interface,
+}
+
+impl UsbTransport {
+    /// Lists the connected boards.
+    pub fn list() -> Result<Vec<DeviceInfo>, TransportError> {
+        let devices = nusb::list_devices().map_err(TransportError::Io)?;
+        Ok(devices.filter(|d| d.vendor_id() == VID && d.product_id() == PID).collect())
+    }
+
+    /// Opens the board with the given serial number, or the first board found.
+    pub fn open(serial_number: Option<&str>) -> Result<Self, TransportError> {
+        let di = Self::list()?
+            .into_iter()
+            .find(|d| serial_number.is_none() || d.serial_number() == serial_number)
+            .ok_or(TransportError::NotFound)?;
+        let device = di.open().map_err(TransportError::Usb)?;
+        Ok(Self::new(device.claim_interface(0).map_err(TransportError::Usb)?))
+    }
+
+    pub fn new(interface: Interface) -> Self {
+        Self { interface }
+    }
+}
+
+impl Transport for UsbTransport {
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), TransportError> {
+        self.interface.bulk_out(BULK_OUT_EP, data).await.status.map_err(transfer_error)
+    }
+
+    async fn receive(&mut self) -> Result<Vec<u8>, TransportError> {
+        match self.interface.bulk_in(BULK_IN_EP, RequestBuffer::new(4096)).await {
+            Completion { data, status: Ok(()) } => Ok(data),
+            Completion { status: Err(e), .. } => Err(transfer_error(e)),
+        }
+    }
+}
+
+/// Tells an unplugged board apart from transfers that failed on a board still there.
+fn transfer_error(e: TransferError) -> TransportError {
+    match e {
+        TransferError::Disconnected => TransportError::Disconnected,
+        e => TransportError::Transfer(e),
+    }
+}
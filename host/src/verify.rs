@@ -0,0 +1,88 @@
+//! Checks the answers a board gives for puzzle inputs against the ones recorded in a manifest, so regressions in the
+//! solvers are caught.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use aoc_2023_icd::solver::Answers;
+use serde::Deserialize;
+
+/// The inputs and their answers, as recorded in `input/answers.toml`.
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    pub input: Vec<Case>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Case {
+    /// The input file, relative to the manifest.
+    pub file: PathBuf,
+    pub day: u8,
+    pub a: u32,
+    pub b: Option<u32>,
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Io(e) => write!(f, "{e}"),
+            ManifestError::Parse(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Manifest {
+    /// Reads the manifest, making the input paths relative to the working directory.
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let text = std::fs::read_to_string(path).map_err(ManifestError::Io)?;
+        let mut manifest: Manifest = toml::from_str(&text).map_err(ManifestError::Parse)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for case in &mut manifest.input {
+            case.file = dir.join(&case.file);
+        }
+        Ok(manifest)
+    }
+}
+
+/// A part the board answered differently than recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    pub part: char,
+    pub expected: Option<u32>,
+    pub got: Option<u32>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |answer: Option<u32>| answer.map_or_else(|| "nothing".to_string(), |a| a.to_string());
+        write!(f, "part {}: expected {}, got {}", self.part, show(self.expected), show(self.got))
+    }
+}
+
+impl Case {
+    /// The parts `answers` gets wrong. Part B is only checked if the manifest records it.
+    pub fn check(&self, answers: &Answers) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        if answers.a != self.a {
+            mismatches.push(Mismatch {
+                part: 'A',
+                expected: Some(self.a),
+                got: Some(answers.a),
+            });
+        }
+        if self.b.is_some() && answers.b != self.b {
+            mismatches.push(Mismatch {
+                part: 'B',
+                expected: self.b,
+                got: answers.b,
+            });
+        }
+        mismatches
+    }
+}
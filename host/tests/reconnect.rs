@@ -0,0 +1,107 @@
+//! Cuts the link to an emulated board in the middle of a run, to check that the run picks up where the board left off
+//! once it is connected again, or starts over when the board forgot what it was fed or was fed by another run.
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+use aoc_2023_host::driver::{self, Protocol, RunOptions};
+use aoc_2023_host::emulator::{self, Firmware};
+use aoc_2023_host::rpc;
+use aoc_2023_host::transport::{AnyTransport, Target};
+use aoc_2023_icd::day1;
+use aoc_2023_icd::error::{WireError, ERROR_PATH};
+use aoc_2023_icd::solver::{Answers, SolveReq, SolveResp};
+use heapless::String;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+/// Forwards the connections to `listener` to the board at `board`, one at a time, cutting the first one off once
+/// `cut` bytes went to the board. Sends how many bytes went to the board on each connection to `sent`. With
+/// `interlope`, another run feeds the board before the host connects again.
+async fn proxy(listener: TcpListener, board: SocketAddr, cut: u64, interlope: bool, sent: mpsc::UnboundedSender<u64>) {
+    let mut limit = cut;
+    loop {
+        let (host, _) = listener.accept().await.unwrap();
+        if interlope && limit != cut {
+            interloper(board).await;
+        }
+        let device = TcpStream::connect(board).await.unwrap();
+        let (mut host_rx, mut host_tx) = host.into_split();
+        let (mut device_rx, mut device_tx) = device.into_split();
+        let mut host_rx = (&mut host_rx).take(limit);
+        // The host hangs up first, unless the link is cut, so the bytes to the board are always counted.
+        tokio::select! {
+            n = tokio::io::copy(&mut host_rx, &mut device_tx) => sent.send(n.unwrap()).unwrap(),
+            _ = tokio::io::copy(&mut device_rx, &mut host_tx) => (),
+        }
+        limit = u64::MAX;
+    }
+}
+
+/// Resets day 1 on the `aoc` board at `board` under a session of its own, and feeds it as many lines as it had, so only
+/// the session tells them from the lines of the run that was cut.
+async fn interloper(board: SocketAddr) {
+    let transport = AnyTransport::open(&Target::Tcp(board.to_string()), Firmware::Aoc).await.unwrap();
+    let (client, _worker) = rpc::new_client::<WireError>(transport, ERROR_PATH, 8);
+    let SolveResp::Lines { session, lines } = client.send_resp::<day1::Solve>(&SolveReq::Lines).await.unwrap() else {
+        panic!("no line count");
+    };
+    client.send_resp::<day1::Solve>(&SolveReq::Reset(session.wrapping_add(1))).await.unwrap();
+    // Batched, so the host isn't kept waiting.
+    for n in (0..lines).step_by(256).map(|from| (lines - from).min(256)) {
+        let batch = vec!["9"; n as usize].join("\n");
+        client.send_resp::<day1::Solve>(&SolveReq::Batch(String::from_str(&batch).unwrap())).await.unwrap();
+    }
+}
+
+/// Runs day 1 on a board running `firmware` behind a link that is cut halfway through the input. Returns the answers,
+/// the answers without the cut, the bytes sent to the board after the cut and the bytes of input.
+async fn run_with_cut(protocol: Protocol, firmware: Firmware, interlope: bool) -> (Answers, Answers, u64, u64) {
+    let input = std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("../input/day1.txt")).unwrap().repeat(20);
+    let expected = driver::run(&Target::Emulator, firmware, protocol, 1, &input, &RunOptions::default()).await.unwrap();
+
+    let board = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let board_addr = board.local_addr().unwrap();
+    tokio::spawn(emulator::serve_tcp(firmware, board));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target = Target::Tcp(listener.local_addr().unwrap().to_string());
+    let (sent, mut sent_rx) = mpsc::unbounded_channel();
+    tokio::spawn(proxy(listener, board_addr, input.len() as u64 / 2, interlope, sent));
+
+    let (link_lost, mut lost) = mpsc::unbounded_channel();
+    let options = RunOptions {
+        link_lost: Some(link_lost),
+        ..RunOptions::default()
+    };
+    let answers = driver::run(&target, firmware, protocol, 1, &input, &options).await.unwrap();
+    assert!(lost.try_recv().is_ok(), "the link wasn't lost");
+    assert!(lost.try_recv().is_err(), "the link was lost more than once");
+    let first = sent_rx.recv().await.unwrap();
+    let second = sent_rx.recv().await.unwrap();
+    assert!(first < input.len() as u64);
+    (answers, expected, second, input.len() as u64)
+}
+
+#[tokio::test]
+async fn aoc_firmware_picks_up_where_it_left_off() {
+    let (answers, expected, second, input) = run_with_cut(Protocol::Solve, Firmware::Aoc, false).await;
+    assert_eq!(answers, expected);
+    // Starting over would have sent the whole input again, along with the framing.
+    assert!(second < input, "{second} bytes sent after the cut, for {input} bytes of input");
+}
+
+#[tokio::test]
+async fn day_firmware_starts_over() {
+    let (answers, expected, second, input) = run_with_cut(Protocol::Native, Firmware::Day(1), false).await;
+    assert_eq!(answers, expected);
+    assert!(second > input, "{second} bytes sent after the cut, for {input} bytes of input");
+}
+
+#[tokio::test]
+async fn aoc_firmware_fed_by_another_run_starts_over() {
+    let (answers, expected, second, input) = run_with_cut(Protocol::Solve, Firmware::Aoc, true).await;
+    assert_eq!(answers, expected);
+    assert!(second > input, "{second} bytes sent after the cut, for {input} bytes of input");
+}
@@ -0,0 +1,26 @@
+//! Runs every input in the answers manifest through an emulated board served on a pseudo-terminal, so the serial
+//! transport carries the same frames as the CDC-ACM port of the `aoc` firmware.
+
+use std::path::Path;
+
+use aoc_2023_host::driver::{self, Protocol, RunOptions};
+use aoc_2023_host::emulator::{self, Firmware};
+use aoc_2023_host::transport::Target;
+use aoc_2023_host::verify::Manifest;
+
+#[tokio::test]
+async fn aoc_firmware_over_a_pty() {
+    let (port, path) = emulator::open_pty().unwrap();
+    tokio::spawn(emulator::serve_stream(Firmware::Aoc, port));
+    let target = Target::Serial(path);
+
+    let manifest = Manifest::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../input/answers.toml")).unwrap();
+    assert!(!manifest.input.is_empty());
+    // One board for every input, opened again for each run, as `run --acm` does.
+    for case in &manifest.input {
+        let input = std::fs::read_to_string(&case.file).unwrap();
+        let answers = driver::run(&target, Firmware::Aoc, Protocol::Solve, case.day, &input, &RunOptions::default()).await;
+        let answers = answers.unwrap_or_else(|e| panic!("{}: {e}", case.file.display()));
+        assert_eq!(case.check(&answers), [], "{}", case.file.display());
+    }
+}
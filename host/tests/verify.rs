@@ -0,0 +1,30 @@
+//! Runs every input in the answers manifest through the emulated board, with both kinds of firmware image, so that a
+//! change to the solvers, the handlers or the drivers that changes an answer fails the tests.
+
+use std::path::Path;
+
+use aoc_2023_host::driver::{self, Protocol, RunOptions};
+use aoc_2023_host::emulator::Firmware;
+use aoc_2023_host::transport::Target;
+use aoc_2023_host::verify::Manifest;
+
+async fn verify(protocol: Protocol, firmware: impl Fn(u8) -> Firmware) {
+    let manifest = Manifest::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../input/answers.toml")).unwrap();
+    assert!(!manifest.input.is_empty());
+    for case in &manifest.input {
+        let input = std::fs::read_to_string(&case.file).unwrap();
+        let answers = driver::run(&Target::Emulator, firmware(case.day), protocol, case.day, &input, &RunOptions::default()).await;
+        let answers = answers.unwrap_or_else(|e| panic!("{}: {e}", case.file.display()));
+        assert_eq!(case.check(&answers), [], "{}", case.file.display());
+    }
+}
+
+#[tokio::test]
+async fn aoc_firmware() {
+    verify(Protocol::Solve, |_| Firmware::Aoc).await;
+}
+
+#[tokio::test]
+async fn day_firmware() {
+    verify(Protocol::Native, Firmware::Day).await;
+}
@@ -3,31 +3,522 @@
 
 pub const VID: u16 = 0xc0de;
 pub const PID: u16 = 0xcafe;
+/// The TCP port the board serves the protocol on, over its USB ethernet function.
+pub const TCP_PORT: u16 = 2023;
+/// The interface number of the CDC-ACM function the `aoc` board serves the protocol on, which follows the vendor
+/// function's interface 0. The console's CDC-ACM function comes after it.
+pub const ACM_INTERFACE: u8 = 1;
 
-pub mod day1 {
+pub mod error {
+    use postcard::experimental::schema::Schema;
+    use serde::{Deserialize, Serialize};
+
+    /// Where the board sends a `WireError` instead of the response, keyed with the sequence number of the failed request.
+    pub const ERROR_PATH: &str = "error";
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Schema)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum WireError {
+        /// No endpoint is registered for the request's key.
+        UnknownEndpoint,
+        /// The request body couldn't be deserialized.
+        Decode,
+        /// The request doesn't fit into one of the board's buffers.
+        Capacity,
+        /// A puzzle line couldn't be parsed. Lines count from 1 since the last reset, columns from 0.
+        Parse {
+            line: u32,
+            column: u32,
+        },
+        Internal,
+        /// A chunk of a line arrived out of order, so the board dropped what it had of the line.
+        OutOfOrder,
+    }
+}
+
+pub mod chunk {
     use heapless::String;
+    use postcard::experimental::schema::Schema;
+    use serde::{Deserialize, Serialize};
+
+    use crate::error::WireError;
+
+    /// Longest line the board reassembles from chunks. The solvers take whole lines, so this caps the line length;
+    /// longer lines are failed with `WireError::Capacity`, and the host refuses to send them. Chunking is deliberately
+    /// partial in this way: the solvers aren't fed the chunks as they come.
+    pub const LINE_SIZE: usize = 1024;
+
+    /// A piece of a puzzle line, so lines of up to `LINE_SIZE` bytes fit into the fixed-size requests. Lines are split
+    /// at char boundaries, into chunks of at most `N` bytes.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
+    pub struct Chunk<const N: usize> {
+        /// Which line the chunk belongs to, counting from 0 since the last reset.
+        pub line: u32,
+        /// Position of the chunk within its line, counting from 0.
+        pub index: u32,
+        /// Set on the last chunk of the line.
+        pub last: bool,
+        pub data: String<N>,
+    }
+
+    /// Splits `text` into the chunks for line number `line`. An empty line is sent as a single empty chunk.
+    ///
+    /// `N` must be at least 4, so every chunk can hold any char.
+    pub fn chunks<const N: usize>(line: u32, text: &str) -> impl Iterator<Item = Chunk<N>> + '_ {
+        let mut rest = Some(text);
+        let mut index = 0;
+        core::iter::from_fn(move || {
+            let text = rest?;
+            let mut end = text.len().min(N);
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (data, tail) = text.split_at(end);
+            rest = (!tail.is_empty()).then_some(tail);
+            let mut chunk = Chunk {
+                line,
+                index,
+                last: rest.is_none(),
+                data: String::new(),
+            };
+            chunk.data.push_str(data).ok()?;
+            index += 1;
+            Some(chunk)
+        })
+    }
+
+    /// Puts lines back together from their chunks, on the board.
+    #[derive(Debug, Default)]
+    pub struct Reassembler<const N: usize> {
+        /// The line being reassembled and the index of the chunk expected next, `None` between lines.
+        next: Option<(u32, u32)>,
+        buf: String<N>,
+    }
+
+    impl<const N: usize> Reassembler<N> {
+        pub const fn new() -> Self {
+            Self { next: None, buf: String::new() }
+        }
+
+        /// Drops whatever it has of the current line.
+        pub fn reset(&mut self) {
+            self.next = None;
+            self.buf.clear();
+        }
+
+        /// Returns the whole line once its last chunk arrives. A chunk with index 0 always starts a new line.
+        pub fn push<const C: usize>(&mut self, chunk: &Chunk<C>) -> Result<Option<&str>, WireError> {
+            if chunk.index == 0 {
+                self.buf.clear();
+            } else if self.next != Some((chunk.line, chunk.index)) {
+                self.reset();
+                return Err(WireError::OutOfOrder);
+            }
+            if self.buf.push_str(&chunk.data).is_err() {
+                self.reset();
+                return Err(WireError::Capacity);
+            }
+            if chunk.last {
+                self.next = None;
+                Ok(Some(&self.buf))
+            } else {
+                self.next = Some((chunk.line, chunk.index + 1));
+                Ok(None)
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn reassemble<const N: usize>(chunks: impl Iterator<Item = Chunk<N>>) -> Option<String<LINE_SIZE>> {
+            let mut reassembler = Reassembler::<LINE_SIZE>::new();
+            let mut line = None;
+            for chunk in chunks {
+                assert!(line.is_none(), "chunks after the last one");
+                line = reassembler.push(&chunk).unwrap().map(|l| l.into());
+            }
+            line
+        }
+
+        #[test]
+        fn long_line_round_trips() {
+            let line = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
+            let pieces: heapless::Vec<Chunk<8>, 8> = chunks(0, line).collect();
+            assert_eq!(pieces.len(), 7);
+            assert!(pieces.iter().enumerate().all(|(i, c)| c.index == i as u32 && c.last == (i == 6)));
+            assert_eq!(reassemble(pieces.into_iter()).unwrap(), line);
+        }
+
+        #[test]
+        fn empty_line_is_one_empty_chunk() {
+            let mut pieces = chunks::<8>(3, "");
+            assert_eq!(
+                pieces.next(),
+                Some(Chunk {
+                    line: 3,
+                    index: 0,
+                    last: true,
+                    data: String::new()
+                })
+            );
+            assert_eq!(pieces.next(), None);
+            assert_eq!(reassemble(chunks::<8>(3, "")).unwrap(), "");
+        }
+
+        #[test]
+        fn multibyte_chars_are_not_split() {
+            // 'é' is two bytes and '€' three, so neither lands on a 4-byte boundary.
+            let line = "aé€€é€b";
+            for chunk in chunks::<4>(0, line) {
+                assert!(!chunk.data.is_empty() && chunk.data.len() <= 4);
+            }
+            assert_eq!(reassemble(chunks::<4>(0, line)).unwrap(), line);
+        }
+
+        #[test]
+        fn out_of_order_chunk_drops_the_line() {
+            let mut reassembler = Reassembler::<LINE_SIZE>::new();
+            let pieces: heapless::Vec<Chunk<4>, 4> = chunks(0, "one two").collect();
+            assert_eq!(reassembler.push(&pieces[0]), Ok(None));
+            assert_eq!(reassembler.push(&Chunk { line: 1, ..pieces[1].clone() }), Err(WireError::OutOfOrder));
+            // What it had of the line is gone, so the rest of it is out of order too.
+            assert_eq!(reassembler.push(&pieces[1]), Err(WireError::OutOfOrder));
+            // Resending the line from its first chunk works.
+            assert_eq!(reassembler.push(&pieces[0]), Ok(None));
+            assert_eq!(reassembler.push(&pieces[1]), Ok(Some("one two")));
+        }
+
+        #[test]
+        fn too_long_line_is_a_capacity_error() {
+            let mut reassembler = Reassembler::<8>::new();
+            let pieces: heapless::Vec<Chunk<4>, 4> = chunks(0, "123456789").collect();
+            assert_eq!(reassembler.push(&pieces[0]), Ok(None));
+            assert_eq!(reassembler.push(&pieces[1]), Ok(None));
+            assert_eq!(reassembler.push(&pieces[2]), Err(WireError::Capacity));
+        }
+    }
+}
+
+pub mod info {
+    use heapless::{String, Vec};
+    use postcard::experimental::schema::Schema;
+    use postcard_rpc::{endpoint, Endpoint};
+    use serde::{Deserialize, Serialize};
+
+    endpoint!(GetInfo, (), Info, "info");
+
+    /// Bumped on changes to the framing or the meaning of messages, which the schema keys don't catch.
+    pub const PROTOCOL_VERSION: u16 = 2;
+
+    /// What the board was flashed with. Every firmware image serves this, and its schema must never change.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
+    pub struct Info {
+        pub protocol_version: u16,
+        pub firmware_version: String<16>,
+        pub git_hash: String<16>,
+        /// `debug` or `release`.
+        pub profile: String<8>,
+        pub days: Vec<u8, 25>,
+        /// Largest request frame the board can receive.
+        pub read_size: u32,
+        /// Largest response frame the board can send.
+        pub write_size: u32,
+        pub endpoints: Vec<EndpointKeys, 16>,
+    }
+
+    /// Identifies an endpoint along with the schemas of its request and response.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
+    pub struct EndpointKeys {
+        pub path: String<32>,
+        pub req_key: [u8; 8],
+        pub resp_key: [u8; 8],
+    }
+
+    impl EndpointKeys {
+        pub fn of<E: Endpoint>() -> Self {
+            Self {
+                path: E::PATH.into(),
+                req_key: E::REQ_KEY.to_bytes(),
+                resp_key: E::RESP_KEY.to_bytes(),
+            }
+        }
+    }
+}
+
+pub mod solver {
+    use heapless::{String, Vec};
+    use postcard::experimental::schema::Schema;
+    use postcard_rpc::endpoint;
     use serde::{Deserialize, Serialize};
 
-    #[derive(Serialize, Deserialize, Debug)]
+    use crate::chunk::Chunk;
+    use crate::error::WireError;
+
+    endpoint!(ListDays, (), Vec<u8, 25>, "days");
+
+    /// A puzzle that is solved by streaming its input one line at a time.
+    pub trait Solver {
+        /// Forgets all lines fed so far.
+        fn reset(&mut self);
+        fn feed(&mut self, line: &str) -> Result<(), SolveError>;
+        /// The answers for the lines fed so far, which `finish` may still change.
+        fn answers(&self) -> Answers;
+        /// The answers if no more lines follow. Some puzzles can only settle the last line once they know it is the
+        /// last. Lines fed afterwards carry on as if it hadn't been called, so it can be asked for in the middle.
+        fn finish(&mut self) -> Result<Answers, SolveError>;
+    }
+
+    pub fn solve<'a>(solver: &mut impl Solver, lines: impl IntoIterator<Item = &'a str>) -> Result<Answers, SolveError> {
+        solver.reset();
+        for line in lines {
+            solver.feed(line)?;
+        }
+        solver.finish()
+    }
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Schema)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Answers {
+        pub a: u32,
+        /// `None` until part B has been solved for the day.
+        pub b: Option<u32>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Schema)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum SolveError {
+        Parse { column: u32 },
+        Capacity,
+    }
+
+    impl SolveError {
+        /// The error to send to the host, given which line was being fed.
+        pub fn at_line(self, line: u32) -> WireError {
+            match self {
+                SolveError::Parse { column } => WireError::Parse { line, column },
+                SolveError::Capacity => WireError::Capacity,
+            }
+        }
+    }
+
+    /// Most bytes of a line carried by one `SolveReq::Line`.
+    pub const CHUNK_SIZE: usize = 256;
+    /// Most bytes of lines carried by one `SolveReq::Batch`, though the board's `read_size` usually allows for less.
+    pub const BATCH_SIZE: usize = 1024;
+
+    /// Request for any day's `Solve` endpoint. Lines that can't be solved are answered with a `WireError`.
+    #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
+    pub enum SolveReq {
+        /// Starts over, under a session token the host picks, so it can tell its own lines from those of another run.
+        Reset(u32),
+        Line(Chunk<CHUNK_SIZE>),
+        /// Whole lines separated by `\n`, acknowledged all at once. Lines too long for a batch are sent as `Line`s.
+        Batch(String<BATCH_SIZE>),
+        Finish,
+        /// Asks how many lines were fed since the last reset, answered with `SolveResp::Lines`, so the host can pick a
+        /// run up where it left off after losing the link.
+        Lines,
+    }
+
+    /// How many bytes of lines fit into a `SolveReq::Batch` for a board that receives frames of up to `read_size`
+    /// bytes. Leaves room for the header, the variant and length, and the COBS framing.
+    pub fn batch_capacity(read_size: usize) -> usize {
+        // Key, sequence number, variant and length, plus a COBS overhead byte per 254 bytes and the terminator.
+        let overhead = 8 + 5 + 1 + 2 + read_size / 254 + 2;
+        read_size.saturating_sub(overhead).min(BATCH_SIZE)
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
+    pub enum SolveResp {
+        Ack,
+        Answers(Answers),
+        /// The lines fed since the last reset, under the session token it was sent with.
+        Lines { session: u32, lines: u32 },
+    }
+}
+
+pub mod progress {
+    use postcard::experimental::schema::Schema;
+    use postcard_rpc::topic;
+    use serde::{Deserialize, Serialize};
+
+    use crate::solver::Answers;
+
+    topic!(ProgressTopic, Progress, "progress");
+
+    /// Published by the board every now and then while it is fed lines, and once more with the final answers.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Progress {
+        pub day: u8,
+        /// Lines fed since the last reset.
+        pub lines: u32,
+        /// The answers so far.
+        pub answers: Answers,
+        /// Bytes left in the board's receive buffer.
+        pub free: u32,
+        pub uptime_ms: u32,
+    }
+}
+
+pub mod log {
+    use heapless::String;
+    use postcard::experimental::schema::Schema;
+    use postcard_rpc::topic;
+    use serde::{Deserialize, Serialize};
+
+    topic!(LogTopic, LogRecord, "log");
+
+    /// Longest log message the board sends, longer ones are cut short.
+    pub const MESSAGE_SIZE: usize = 96;
+
+    /// Ordered from the most to the least verbose.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Schema)]
+    pub enum Level {
+        Trace,
+        Debug,
+        Info,
+        Warn,
+        Error,
+    }
+
+    /// A line the board logged, so it can be read without a debug probe.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
+    pub struct LogRecord {
+        pub level: Level,
+        /// Milliseconds since the board booted.
+        pub timestamp_ms: u32,
+        pub message: String<MESSAGE_SIZE>,
+    }
+}
+
+pub mod stats {
+    use heapless::Vec;
+    use postcard::experimental::schema::Schema;
+    use postcard_rpc::endpoint;
+    use serde::{Deserialize, Serialize};
+
+    endpoint!(GetStats, (), Stats, "stats");
+
+    /// Performance counters of the board since it booted.
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Schema)]
+    pub struct Stats {
+        /// Requests handled, with the time spent on them. Sending the responses isn't counted.
+        pub total: Timing,
+        /// The same, for each day the board was fed.
+        pub days: Vec<DayTiming, 25>,
+        /// The longest single request, in microseconds.
+        pub max_us: u32,
+        pub link: LinkStats,
+        /// The most stack the board ever used, in bytes, if it measures that.
+        pub peak_stack: Option<u32>,
+    }
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Schema)]
+    pub struct Timing {
+        pub requests: u32,
+        pub busy_us: u64,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Schema)]
+    pub struct DayTiming {
+        pub day: u8,
+        pub timing: Timing,
+    }
+
+    /// What the board's receive loop saw of the link.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Schema)]
+    pub struct LinkStats {
+        /// The most bytes ever queued in the receive buffer.
+        pub peak_used: u32,
+        /// Frames dropped because they didn't fit into the receive buffer.
+        pub overfull: u32,
+        /// Frames dropped because they weren't valid COBS.
+        pub corrupt: u32,
+    }
+
+    impl Timing {
+        pub fn since(&self, earlier: &Timing) -> Timing {
+            Timing {
+                requests: self.requests.wrapping_sub(earlier.requests),
+                busy_us: self.busy_us.wrapping_sub(earlier.busy_us),
+            }
+        }
+    }
+
+    impl Stats {
+        /// The counters accumulated between `earlier` and `self`. The peaks are kept as they are, since they can't be
+        /// told apart.
+        pub fn since(&self, earlier: &Stats) -> Stats {
+            let mut days = self.days.clone();
+            for day in &mut days {
+                if let Some(before) = earlier.days.iter().find(|d| d.day == day.day) {
+                    day.timing = day.timing.since(&before.timing);
+                }
+            }
+            days.retain(|d| d.timing.requests > 0);
+            Stats {
+                total: self.total.since(&earlier.total),
+                days,
+                link: LinkStats {
+                    overfull: self.link.overfull.wrapping_sub(earlier.link.overfull),
+                    corrupt: self.link.corrupt.wrapping_sub(earlier.link.corrupt),
+                    ..self.link
+                },
+                ..self.clone()
+            }
+        }
+    }
+}
+
+pub mod day1 {
+    use postcard::experimental::schema::Schema;
+    use postcard_rpc::endpoint;
+    use serde::{Deserialize, Serialize};
+
+    use crate::chunk::Chunk;
+    use crate::solver::{SolveReq, SolveResp};
+
+    endpoint!(Solve, SolveReq, SolveResp, "day1/solve");
+    endpoint!(Calibration, HostToClient, ClientToHost, "calibration");
+
+    /// Most bytes of a line carried by one `HostToClient::Data`.
+    pub const CHUNK_SIZE: usize = 64;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
     //#[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub enum HostToClient {
-        Data(String<64>),
+        Data(Chunk<CHUNK_SIZE>),
         GetResultA,
         GetResultB,
         Reset,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
     //#[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub enum ClientToHost {
+        /// Answers `Data` and `Reset`.
+        Ack,
         Result(u32),
     }
 }
 
 pub mod day2 {
-    use heapless::String;
+    use postcard::experimental::schema::Schema;
+    use postcard_rpc::endpoint;
     use serde::{Deserialize, Serialize};
 
+    use crate::chunk::Chunk;
+    use crate::solver::{SolveReq, SolveResp};
+
+    endpoint!(Solve, SolveReq, SolveResp, "day2/solve");
+    endpoint!(Cubes, HostToClient, ClientToHost, "cubes");
+
+    /// Most bytes of a line carried by one `HostToClient::GameData`.
+    pub const CHUNK_SIZE: usize = 512;
+
     pub trait StateMachine {
         async fn start(&mut self) -> Result<(), ()>;
         async fn write_next_game(&mut self) -> Result<(), ()>;
@@ -40,16 +531,16 @@ pub mod day2 {
         sm.end().await
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
     //#[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub enum HostToClient {
         Start,
-        GameData(String<1024>),
-        End,
+        /// Answered with `GameDataWritten` for every chunk.
+        GameData(Chunk<CHUNK_SIZE>),
         GetResult,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub enum ClientToHost {
         Started,
@@ -59,42 +550,58 @@ pub mod day2 {
 }
 
 pub mod day3 {
-    use heapless::{String, Vec};
+    use heapless::Vec;
     use postcard::experimental::schema::Schema;
     use postcard_rpc::endpoint;
     use serde::{Deserialize, Serialize};
 
+    use crate::chunk::Chunk;
+    use crate::solver::{Answers, SolveReq, SolveResp};
+
+    endpoint!(Solve, SolveReq, SolveResp, "day3/solve");
     endpoint!(Engine, EngineReq, EngineResp, "engine");
+    endpoint!(GetResult, (), Answers, "engine/result");
+
+    /// Row and column in the schematic. Widen it for bigger grids; the board reports `Capacity` for anything beyond.
+    pub type Coord = u16;
+    /// Most numbers, and most symbols, on a single line.
+    pub const LINE_CAPACITY: usize = 128;
+    /// Most parts a single line can confirm.
+    pub const PARTS_CAPACITY: usize = 64;
+    /// Most `*` on a single line, and so most gears a single line can confirm.
+    pub const GEARS_CAPACITY: usize = 32;
+    /// Most bytes of a line carried by one `EngineReq::Data`.
+    pub const CHUNK_SIZE: usize = 256;
 
     #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
     pub enum EngineReq {
         Reset,
-        Data(String<256>),
+        /// Answered with an empty `EngineResp` for every chunk but the last.
+        Data(Chunk<CHUNK_SIZE>),
+        /// Sent after the last line, to settle the gears on it. Sent before, it reports the gears as if the input ended
+        /// there, and the next line confirms them as usual.
+        Finish,
     }
 
-    #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
+    /// Every part and gear is confirmed exactly once, so the host may keep its own tally, but `GetResult` has the sums.
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize, Schema)]
     pub struct EngineResp {
-        pub result: Vec<Number, 64>,
-    }
-
-    #[derive(Debug, PartialEq, Serialize, Deserialize, Schema)]
-    pub enum WireError {
-        LeastBad,
-        MediumBad,
-        MostBad,
+        pub result: Vec<Number, PARTS_CAPACITY>,
+        /// Ratios of the gears confirmed by the request.
+        pub gears: Vec<u32, GEARS_CAPACITY>,
     }
 
     #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Schema)]
     pub struct Number {
-        pub x: (u8, u8),
-        pub y: u8,
-        pub value: u16,
+        pub x: (Coord, Coord),
+        pub y: Coord,
+        pub value: u32,
     }
 
     #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
     pub struct Symbol {
-        pub x: u8,
-        pub y: u8,
+        pub x: Coord,
+        pub y: Coord,
         pub symbol: char,
     }
 }
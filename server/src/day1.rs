@@ -0,0 +1,36 @@
+use aoc_2023_icd::chunk::{Reassembler, LINE_SIZE};
+use aoc_2023_icd::day1::{ClientToHost, HostToClient};
+use aoc_2023_icd::error::WireError;
+use aoc_2023_solvers::day1::Trebuchet;
+
+#[derive(Debug, Default)]
+pub struct Handler {
+    trebuchet: Trebuchet,
+    chunks: Reassembler<LINE_SIZE>,
+}
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self {
+            trebuchet: Trebuchet::new(),
+            chunks: Reassembler::new(),
+        }
+    }
+
+    pub fn handle(&mut self, msg: HostToClient) -> Result<ClientToHost, WireError> {
+        match msg {
+            HostToClient::Data(chunk) => {
+                if let Some(line) = self.chunks.push(&chunk)? {
+                    self.trebuchet.feed(line);
+                }
+            }
+            HostToClient::GetResultA => return Ok(ClientToHost::Result(self.trebuchet.result_a())),
+            HostToClient::GetResultB => return Ok(ClientToHost::Result(self.trebuchet.result_b())),
+            HostToClient::Reset => {
+                self.trebuchet.reset();
+                self.chunks.reset();
+            }
+        }
+        Ok(ClientToHost::Ack)
+    }
+}
@@ -0,0 +1,41 @@
+use aoc_2023_icd::chunk::{Reassembler, LINE_SIZE};
+use aoc_2023_icd::day2::{ClientToHost, HostToClient};
+use aoc_2023_icd::error::WireError;
+use aoc_2023_solvers::day2::CubeConundrum;
+
+#[derive(Debug, Default)]
+pub struct Handler {
+    cubes: CubeConundrum,
+    /// Games written since the last `Start`.
+    line: u32,
+    chunks: Reassembler<LINE_SIZE>,
+}
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self {
+            cubes: CubeConundrum::new(),
+            line: 0,
+            chunks: Reassembler::new(),
+        }
+    }
+
+    pub fn handle(&mut self, msg: HostToClient) -> Result<ClientToHost, WireError> {
+        match msg {
+            HostToClient::Start => {
+                self.cubes.reset();
+                self.line = 0;
+                self.chunks.reset();
+                Ok(ClientToHost::Started)
+            }
+            HostToClient::GameData(chunk) => {
+                if let Some(line) = self.chunks.push(&chunk)? {
+                    self.line += 1;
+                    self.cubes.feed(line).map_err(|e| e.at_line(self.line))?;
+                }
+                Ok(ClientToHost::GameDataWritten)
+            }
+            HostToClient::GetResult => Ok(ClientToHost::Result(self.cubes.results())),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use aoc_2023_icd::chunk::{Reassembler, LINE_SIZE};
+use aoc_2023_icd::day3::{EngineReq, EngineResp};
+use aoc_2023_icd::error::WireError;
+use aoc_2023_icd::solver::{Answers, Solver};
+use aoc_2023_solvers::day3::EngineSchematic;
+use heapless::Vec;
+
+#[derive(Debug, Default)]
+pub struct Handler {
+    schematic: EngineSchematic,
+    /// Lines fed since the last reset.
+    line: u32,
+    chunks: Reassembler<LINE_SIZE>,
+}
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self {
+            schematic: EngineSchematic::new(),
+            line: 0,
+            chunks: Reassembler::new(),
+        }
+    }
+
+    pub fn handle(&mut self, req: EngineReq) -> Result<EngineResp, WireError> {
+        match req {
+            EngineReq::Reset => {
+                self.schematic.reset();
+                self.line = 0;
+                self.chunks.reset();
+                Ok(EngineResp::default())
+            }
+            EngineReq::Data(chunk) => {
+                let Some(line) = self.chunks.push(&chunk)? else {
+                    return Ok(EngineResp::default());
+                };
+                let confirmed = self.schematic.feed(line).map_err(|e| e.at_line(self.line + 1))?;
+                self.line += 1;
+                Ok(EngineResp {
+                    result: confirmed.parts,
+                    gears: confirmed.gears,
+                })
+            }
+            EngineReq::Finish => Ok(EngineResp {
+                result: Vec::new(),
+                gears: self.schematic.finish().map_err(|e| e.at_line(self.line))?,
+            }),
+        }
+    }
+
+    /// Returns the sums of everything fed since the last reset, settling the last line as if no more followed.
+    pub fn result(&mut self) -> Result<Answers, WireError> {
+        Solver::finish(&mut self.schematic).map_err(|e| e.at_line(self.line))
+    }
+}
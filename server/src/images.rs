@@ -0,0 +1,322 @@
+//! The request dispatchers of the firmware images: the combined `aoc` one and the single-day `dayN` ones. The firmware
+//! runs them on its links and the host emulator on a tokio stream, each through its own [`Platform`].
+
+use core::fmt;
+
+use aoc_2023_icd::day1::{Calibration, ClientToHost as Day1Resp};
+use aoc_2023_icd::day2::{ClientToHost as Day2Resp, Cubes};
+use aoc_2023_icd::day3::{Engine, EngineReq, GetResult};
+use aoc_2023_icd::error::WireError;
+use aoc_2023_icd::info::{EndpointKeys, GetInfo, Info, PROTOCOL_VERSION};
+use aoc_2023_icd::log::{Level, LogRecord, LogTopic};
+use aoc_2023_icd::progress::{Progress, ProgressTopic};
+use aoc_2023_icd::solver::{ListDays, SolveReq, SolveResp};
+use aoc_2023_icd::stats::GetStats;
+use aoc_2023_icd::{day1, day2, day3};
+use embedded_io_async::{Read, Write};
+use heapless::Vec;
+use postcard_rpc::{Endpoint, WireHeader};
+
+use crate::rpc::{decode, Dispatch, Error, Server};
+use crate::solve::{Solvers, DAYS};
+use crate::stats::Recorder;
+
+/// The buffer sizes of the servers on the board's links.
+pub const READ_SIZE: usize = 1024;
+pub const WRITE_SIZE: usize = 4096;
+
+pub type BoardServer<S> = Server<S, READ_SIZE, WRITE_SIZE>;
+
+/// How often the `aoc` image publishes `Progress` while it is fed lines, in microseconds.
+pub const PROGRESS_INTERVAL_US: u64 = 100_000;
+
+/// What the images need from whatever runs them.
+pub trait Platform {
+    /// Describes the build, for `GetInfo`.
+    fn build(&self) -> Build;
+    /// Microseconds since the board booted.
+    fn now_us(&self) -> u64;
+    /// Logs a line, keeping it for `pop_log` so it can be published on the log topic.
+    fn log(&mut self, level: Level, args: fmt::Arguments);
+    /// The oldest line logged but not published yet.
+    fn pop_log(&mut self) -> Option<LogRecord>;
+    /// The most bytes of stack used so far, where that can be told.
+    fn peak_stack(&self) -> Option<u32>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Build {
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    /// `debug` or `release`.
+    pub profile: &'static str,
+}
+
+/// Describes the build, serving the given days through the given endpoints. `GetInfo` is always listed.
+pub fn info(build: Build, days: &[u8], endpoints: &[EndpointKeys]) -> Info {
+    let mut all = Vec::new();
+    all.push(EndpointKeys::of::<GetInfo>()).unwrap();
+    all.extend(endpoints.iter().cloned());
+    Info {
+        protocol_version: PROTOCOL_VERSION,
+        firmware_version: build.version.into(),
+        git_hash: build.git_hash.into(),
+        profile: build.profile.into(),
+        days: Vec::from_slice(days).unwrap(),
+        read_size: READ_SIZE as u32,
+        write_size: WRITE_SIZE as u32,
+        endpoints: all,
+    }
+}
+
+/// Publishes the lines logged since the last call, numbered by `seq_no`, which is advanced past them.
+pub async fn publish_logs<S: Read + Write>(server: &mut BoardServer<S>, platform: &mut impl Platform, seq_no: &mut u32) -> Result<(), Error<S::Error>> {
+    while let Some(record) = platform.pop_log() {
+        *seq_no = seq_no.wrapping_add(1);
+        server.publish::<LogTopic>(*seq_no, &record).await?;
+    }
+    Ok(())
+}
+
+/// The `aoc` image, serving every day. It keeps the lines fed to each day between connections.
+pub struct Aoc<P> {
+    platform: P,
+    log_seq: u32,
+    solvers: Solvers,
+    reporter: Reporter,
+    recorder: Recorder,
+}
+
+impl<P: Platform> Aoc<P> {
+    pub fn new(platform: P) -> Self {
+        Self {
+            platform,
+            log_seq: 0,
+            solvers: Solvers::new(),
+            reporter: Reporter::new(),
+            recorder: Recorder::new(),
+        }
+    }
+
+    async fn solve<E, S>(&mut self, server: &mut BoardServer<S>, day: u8, seq_no: u32, body: &[u8]) -> Result<(), Error<S::Error>>
+    where
+        E: Endpoint<Request = SolveReq, Response = SolveResp>,
+        S: Read + Write,
+    {
+        let start = self.platform.now_us();
+        let resp = decode(body).and_then(|req| {
+            if let SolveReq::Reset(_) = req {
+                self.platform.log(Level::Info, format_args!("RESET day {day}"));
+            }
+            self.solvers.handle(day, req)
+        });
+        self.recorder.record(day, self.platform.now_us().saturating_sub(start) as u32);
+        match &resp {
+            Ok(SolveResp::Answers(answers)) => self.platform.log(Level::Info, format_args!("Day {day}: {answers:?}")),
+            Err(e) => self.platform.log(Level::Warn, format_args!("Day {day}: {e:?}")),
+            _ => (),
+        }
+        let finished = matches!(resp, Ok(SolveResp::Answers(_)));
+        server.respond::<E>(seq_no, resp).await?;
+        self.reporter.report(server, &self.platform, &self.solvers, day, finished).await
+    }
+}
+
+impl<P: Platform> Dispatch<READ_SIZE, WRITE_SIZE> for Aoc<P> {
+    async fn dispatch<S: Read + Write>(&mut self, server: &mut BoardServer<S>, hdr: WireHeader, body: &[u8]) -> Result<(), Error<S::Error>> {
+        match hdr.key {
+            GetInfo::REQ_KEY => server.reply::<GetInfo>(hdr.seq_no, &info(self.platform.build(), DAYS, &aoc_endpoints())).await?,
+            ListDays::REQ_KEY => server.reply::<ListDays>(hdr.seq_no, &Vec::from_slice(DAYS).unwrap()).await?,
+            GetStats::REQ_KEY => {
+                let stats = self.recorder.stats(server.frames().stats(), self.platform.peak_stack());
+                server.reply::<GetStats>(hdr.seq_no, &stats).await?
+            }
+            day1::Solve::REQ_KEY => self.solve::<day1::Solve, S>(server, 1, hdr.seq_no, body).await?,
+            day2::Solve::REQ_KEY => self.solve::<day2::Solve, S>(server, 2, hdr.seq_no, body).await?,
+            day3::Solve::REQ_KEY => self.solve::<day3::Solve, S>(server, 3, hdr.seq_no, body).await?,
+            _ => server.error(hdr.seq_no, WireError::UnknownEndpoint).await?,
+        }
+        publish_logs(server, &mut self.platform, &mut self.log_seq).await
+    }
+}
+
+fn aoc_endpoints() -> [EndpointKeys; 5] {
+    [
+        EndpointKeys::of::<ListDays>(),
+        EndpointKeys::of::<GetStats>(),
+        EndpointKeys::of::<day1::Solve>(),
+        EndpointKeys::of::<day2::Solve>(),
+        EndpointKeys::of::<day3::Solve>(),
+    ]
+}
+
+/// Publishes `Progress` at most every `PROGRESS_INTERVAL_US`, and whenever a day is finished.
+struct Reporter {
+    last_us: Option<u64>,
+    seq_no: u32,
+}
+
+impl Reporter {
+    fn new() -> Self {
+        Self { last_us: None, seq_no: 0 }
+    }
+
+    async fn report<S: Read + Write>(&mut self, server: &mut BoardServer<S>, platform: &impl Platform, solvers: &Solvers, day: u8, finished: bool) -> Result<(), Error<S::Error>> {
+        let now_us = platform.now_us();
+        if !finished && self.last_us.is_some_and(|last_us| now_us.saturating_sub(last_us) < PROGRESS_INTERVAL_US) {
+            return Ok(());
+        }
+        let Some((lines, answers)) = solvers.status(day) else {
+            return Ok(());
+        };
+        self.last_us = Some(now_us);
+        self.seq_no = self.seq_no.wrapping_add(1);
+        let progress = Progress {
+            day,
+            lines,
+            answers,
+            free: server.frames().free() as u32,
+            uptime_ms: (now_us / 1000) as u32,
+        };
+        server.publish::<ProgressTopic>(self.seq_no, &progress).await
+    }
+}
+
+/// The `day1` image.
+pub struct Day1<P> {
+    platform: P,
+    log_seq: u32,
+    handler: crate::day1::Handler,
+}
+
+impl<P: Platform> Day1<P> {
+    pub fn new(platform: P) -> Self {
+        Self {
+            platform,
+            log_seq: 0,
+            handler: crate::day1::Handler::new(),
+        }
+    }
+
+    /// Starts the handler afresh, for a new connection.
+    pub fn reset(&mut self) {
+        self.handler = crate::day1::Handler::new();
+    }
+}
+
+impl<P: Platform> Dispatch<READ_SIZE, WRITE_SIZE> for Day1<P> {
+    async fn dispatch<S: Read + Write>(&mut self, server: &mut BoardServer<S>, hdr: WireHeader, body: &[u8]) -> Result<(), Error<S::Error>> {
+        match hdr.key {
+            GetInfo::REQ_KEY => server.reply::<GetInfo>(hdr.seq_no, &info(self.platform.build(), &[1], &[EndpointKeys::of::<Calibration>()])).await?,
+            Calibration::REQ_KEY => {
+                let resp = decode(body).and_then(|msg| self.handler.handle(msg));
+                if let Ok(Day1Resp::Result(sum)) = resp {
+                    self.platform.log(Level::Info, format_args!("Sum: {sum}"));
+                }
+                server.respond::<Calibration>(hdr.seq_no, resp).await?
+            }
+            _ => server.error(hdr.seq_no, WireError::UnknownEndpoint).await?,
+        }
+        publish_logs(server, &mut self.platform, &mut self.log_seq).await
+    }
+}
+
+/// The `day2` image.
+pub struct Day2<P> {
+    platform: P,
+    log_seq: u32,
+    handler: crate::day2::Handler,
+}
+
+impl<P: Platform> Day2<P> {
+    pub fn new(platform: P) -> Self {
+        Self {
+            platform,
+            log_seq: 0,
+            handler: crate::day2::Handler::new(),
+        }
+    }
+
+    /// Starts the handler afresh, for a new connection.
+    pub fn reset(&mut self) {
+        self.handler = crate::day2::Handler::new();
+    }
+}
+
+impl<P: Platform> Dispatch<READ_SIZE, WRITE_SIZE> for Day2<P> {
+    async fn dispatch<S: Read + Write>(&mut self, server: &mut BoardServer<S>, hdr: WireHeader, body: &[u8]) -> Result<(), Error<S::Error>> {
+        match hdr.key {
+            GetInfo::REQ_KEY => server.reply::<GetInfo>(hdr.seq_no, &info(self.platform.build(), &[2], &[EndpointKeys::of::<Cubes>()])).await?,
+            Cubes::REQ_KEY => {
+                let resp = decode(body).and_then(|msg| self.handler.handle(msg));
+                match resp {
+                    Ok(Day2Resp::Started) => self.platform.log(Level::Info, format_args!("START")),
+                    Ok(Day2Resp::Result((result_a, result_b))) => {
+                        self.platform.log(Level::Info, format_args!("Result A: {result_a}"));
+                        self.platform.log(Level::Info, format_args!("Result B: {result_b}"));
+                    }
+                    Err(e) => self.platform.log(Level::Warn, format_args!("Bad game data: {e:?}")),
+                    _ => (),
+                }
+                server.respond::<Cubes>(hdr.seq_no, resp).await?
+            }
+            _ => server.error(hdr.seq_no, WireError::UnknownEndpoint).await?,
+        }
+        publish_logs(server, &mut self.platform, &mut self.log_seq).await
+    }
+}
+
+/// The `day3` image.
+pub struct Day3<P> {
+    platform: P,
+    log_seq: u32,
+    handler: crate::day3::Handler,
+}
+
+impl<P: Platform> Day3<P> {
+    pub fn new(platform: P) -> Self {
+        Self {
+            platform,
+            log_seq: 0,
+            handler: crate::day3::Handler::new(),
+        }
+    }
+
+    /// Starts the handler afresh, for a new connection.
+    pub fn reset(&mut self) {
+        self.handler = crate::day3::Handler::new();
+    }
+}
+
+impl<P: Platform> Dispatch<READ_SIZE, WRITE_SIZE> for Day3<P> {
+    async fn dispatch<S: Read + Write>(&mut self, server: &mut BoardServer<S>, hdr: WireHeader, body: &[u8]) -> Result<(), Error<S::Error>> {
+        match hdr.key {
+            GetInfo::REQ_KEY => {
+                let endpoints = [EndpointKeys::of::<Engine>(), EndpointKeys::of::<GetResult>()];
+                server.reply::<GetInfo>(hdr.seq_no, &info(self.platform.build(), &[3], &endpoints)).await?
+            }
+            Engine::REQ_KEY => {
+                let resp = decode(body).and_then(|msg| {
+                    if msg == EngineReq::Reset {
+                        self.platform.log(Level::Info, format_args!("RESET"));
+                    }
+                    self.handler.handle(msg)
+                });
+                if let Err(e) = resp {
+                    self.platform.log(Level::Warn, format_args!("Bad schematic line: {e:?}"));
+                }
+                server.respond::<Engine>(hdr.seq_no, resp).await?
+            }
+            GetResult::REQ_KEY => {
+                let result = self.handler.result();
+                if let Ok(answers) = &result {
+                    self.platform.log(Level::Info, format_args!("Result A: {}", answers.a));
+                    self.platform.log(Level::Info, format_args!("Result B: {:?}", answers.b));
+                }
+                server.respond::<GetResult>(hdr.seq_no, result).await?
+            }
+            _ => server.error(hdr.seq_no, WireError::UnknownEndpoint).await?,
+        }
+        publish_logs(server, &mut self.platform, &mut self.log_seq).await
+    }
+}
@@ -0,0 +1,14 @@
+//! Device side of the protocol for each day, independent of how the bytes get to and from the host.
+//!
+//! The firmware feeds these handlers from its USB endpoints and the host emulator feeds them from memory, both through
+//! the dispatchers in `images`.
+#![no_std]
+
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod images;
+pub mod repl;
+pub mod rpc;
+pub mod solve;
+pub mod stats;
@@ -0,0 +1,152 @@
+//! A text mode for poking the board by hand, over any serial-style stream: commands and puzzle lines go in one per
+//! line, and the answers come back as text. Puzzle input can be fed straight from a terminal or from
+//! `cat input | socat - /dev/ttyACM1`.
+
+use core::fmt::Write as _;
+
+use aoc_2023_icd::chunk::LINE_SIZE;
+use aoc_2023_icd::error::WireError;
+use aoc_2023_icd::solver::{Answers, SolveReq, SolveResp};
+use aoc_2023_icd::stats::LinkStats;
+use embedded_io_async::{Read, Write};
+use heapless::{String, Vec};
+
+use crate::solve::{Solvers, DAYS};
+use crate::stats::Recorder;
+
+const HELP: &str = "\
+commands:
+  day N    solve day N from now on
+  reset    forget the lines fed to the day so far
+  result   print the answers to the lines fed so far
+  stats    print how many lines were fed and how long they took
+  help     print this
+any other line is fed to the day as a line of puzzle input, empty lines are skipped
+";
+
+/// Longest reply to a single line.
+const REPLY_SIZE: usize = 512;
+
+/// The state of the text mode between lines.
+pub struct Repl {
+    solvers: Solvers,
+    recorder: Recorder,
+    day: Option<u8>,
+}
+
+impl Repl {
+    pub const fn new() -> Self {
+        Self {
+            solvers: Solvers::new(),
+            recorder: Recorder::new(),
+            day: None,
+        }
+    }
+
+    /// Serves the text mode until the stream ends or fails. `clock` gives the time in microseconds, for `stats`.
+    pub async fn run<S: Read + Write>(&mut self, stream: &mut S, clock: impl Fn() -> u64) -> Result<(), S::Error> {
+        let mut line: Vec<u8, LINE_SIZE> = Vec::new();
+        let mut too_long = false;
+        let mut buf = [0; 64];
+        write_crlf(stream, "aoc 2023, type `help` for the commands\n").await?;
+        loop {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            for &byte in &buf[..n] {
+                match byte {
+                    b'\r' => (),
+                    b'\n' => {
+                        let mut reply = String::<REPLY_SIZE>::new();
+                        let _ = match core::str::from_utf8(&line) {
+                            _ if too_long => writeln!(reply, "error: lines can be at most {LINE_SIZE} bytes"),
+                            Ok(line) => self.handle(line.trim(), &mut reply, &clock),
+                            Err(_) => writeln!(reply, "error: lines must be UTF-8"),
+                        };
+                        write_crlf(stream, &reply).await?;
+                        line.clear();
+                        too_long = false;
+                    }
+                    _ => too_long |= line.push(byte).is_err(),
+                }
+            }
+        }
+    }
+
+    /// Handles a line, writing the reply to `out`. Replies that don't fit are cut short.
+    fn handle(&mut self, line: &str, out: &mut String<REPLY_SIZE>, clock: &impl Fn() -> u64) -> core::fmt::Result {
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("", _) => Ok(()),
+            ("help", "") => out.push_str(HELP).map_err(|_| core::fmt::Error),
+            ("day", day) => match day.parse() {
+                Ok(day) if DAYS.contains(&day) => {
+                    self.day = Some(day);
+                    writeln!(out, "solving day {day}")
+                }
+                _ => writeln!(out, "error: the days are {DAYS:?}"),
+            },
+            ("reset", "") => self.with_day(out, |repl, day, out| match repl.solvers.handle(day, SolveReq::Reset(0)) {
+                Ok(_) => writeln!(out, "day {day} reset"),
+                Err(e) => error(out, e),
+            }),
+            ("result", "") => self.with_day(out, |repl, day, out| match repl.solvers.handle(day, SolveReq::Finish) {
+                Ok(SolveResp::Answers(Answers { a, b: Some(b) })) => writeln!(out, "A: {a}\nB: {b}"),
+                Ok(SolveResp::Answers(Answers { a, b: None })) => writeln!(out, "A: {a}"),
+                Ok(SolveResp::Ack | SolveResp::Lines { .. }) => writeln!(out, "error: no answers"),
+                Err(e) => error(out, e),
+            }),
+            ("stats", "") => self.with_day(out, |repl, day, out| {
+                let (lines, answers) = repl.solvers.status(day).unwrap_or_default();
+                let stats = repl.recorder.stats(LinkStats::default(), None);
+                let timing = stats.days.iter().find(|d| d.day == day).map(|d| d.timing).unwrap_or_default();
+                writeln!(out, "day {day}: {lines} lines since the last reset, A so far: {}", answers.a)?;
+                writeln!(
+                    out,
+                    "{} lines since boot, {} us solving them, slowest line of any day {} us",
+                    timing.requests, timing.busy_us, stats.max_us
+                )
+            }),
+            _ => self.with_day(out, |repl, day, out| {
+                let start = clock();
+                let fed = repl.solvers.feed(day, line);
+                repl.recorder.record(day, clock().saturating_sub(start) as u32);
+                match fed {
+                    Ok(()) => Ok(()),
+                    Err(e) => error(out, e),
+                }
+            }),
+        }
+    }
+
+    fn with_day(&mut self, out: &mut String<REPLY_SIZE>, f: impl FnOnce(&mut Self, u8, &mut String<REPLY_SIZE>) -> core::fmt::Result) -> core::fmt::Result {
+        match self.day {
+            Some(day) => f(self, day, out),
+            None => writeln!(out, "error: pick a day first, with `day N`"),
+        }
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn error(out: &mut String<REPLY_SIZE>, e: WireError) -> core::fmt::Result {
+    match e {
+        WireError::Parse { line, column } => writeln!(out, "error: can't parse line {line} at column {column}"),
+        e => writeln!(out, "error: {e:?}"),
+    }
+}
+
+/// Writes `text` with the line ends terminals expect.
+async fn write_crlf<S: Write>(stream: &mut S, text: &str) -> Result<(), S::Error> {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            stream.write_all(b"\r\n").await?;
+        }
+        stream.write_all(line.as_bytes()).await?;
+    }
+    stream.flush().await
+}
@@ -0,0 +1,205 @@
+//! The postcard-rpc server loop, over any byte stream: the firmware runs it on its USB functions and the host emulator
+//! on a tokio stream, through `embedded-io-adapters`.
+
+use aoc_2023_icd::error::{WireError, ERROR_PATH};
+use aoc_2023_icd::stats::LinkStats;
+use embedded_io_async::{ErrorType, Read, Write};
+use heapless::Vec;
+use postcard_rpc::accumulator::raw::{CobsAccumulator, FeedResult};
+use postcard_rpc::headered::{self, extract_header_from_bytes};
+use postcard_rpc::{Endpoint, Topic, WireHeader};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum Error<E> {
+    IO(E),
+    Postcard(postcard::Error),
+    Heapless,
+    /// The stream ended.
+    Closed,
+}
+
+/// The frames received from the host but not handled yet. It outlives the calls to `Server::receive`, so requests
+/// the host pipelined are queued up rather than dropped, and a frame may span any number of reads.
+pub struct Frames<const N: usize> {
+    cobs_buf: CobsAccumulator<N>,
+    /// Bytes read after the last frame handed out, which may hold further frames.
+    pending: Vec<u8, N>,
+    /// Bytes of the next frame fed to `cobs_buf` so far.
+    partial: usize,
+    stats: LinkStats,
+}
+
+impl<const N: usize> Frames<N> {
+    pub const fn new() -> Self {
+        Self {
+            cobs_buf: CobsAccumulator::new(),
+            pending: Vec::new(),
+            partial: 0,
+            stats: LinkStats {
+                peak_used: 0,
+                overfull: 0,
+                corrupt: 0,
+            },
+        }
+    }
+
+    /// The most bytes queued so far, and the frames dropped.
+    pub fn stats(&self) -> LinkStats {
+        self.stats
+    }
+
+    fn note_used(&mut self) {
+        let used = (self.partial + self.pending.len()).min(N) as u32;
+        self.stats.peak_used = self.stats.peak_used.max(used);
+    }
+
+    /// Bytes left in the receive buffer, minus what was read of the frames queued up.
+    pub fn free(&self) -> usize {
+        N.saturating_sub(self.partial + self.pending.len())
+    }
+
+    /// Returns the next frame completed by the pending bytes, or `None` once they are used up.
+    fn pop(&mut self) -> Option<Result<Vec<u8, N>, ()>> {
+        let mut window = &self.pending[..];
+        let mut frame = None;
+        while frame.is_none() && !window.is_empty() {
+            window = match self.cobs_buf.feed(window) {
+                FeedResult::Consumed => {
+                    self.partial += window.len();
+                    &[]
+                }
+                FeedResult::OverFull(new_wind) => {
+                    self.stats.overfull += 1;
+                    self.partial = 0;
+                    new_wind
+                }
+                FeedResult::DeserError(new_wind) => {
+                    self.stats.corrupt += 1;
+                    self.partial = 0;
+                    new_wind
+                }
+                FeedResult::Success { data, remaining } => {
+                    self.partial = 0;
+                    frame = Some(Vec::from_slice(data));
+                    remaining
+                }
+            };
+        }
+        let consumed = self.pending.len() - window.len();
+        self.pending.rotate_left(consumed);
+        self.pending.truncate(self.pending.len() - consumed);
+        frame
+    }
+}
+
+impl<const N: usize> Default for Frames<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Answers the requests of one kind of board.
+pub trait Dispatch<const READ_SIZE: usize, const WRITE_SIZE: usize> {
+    /// Handles a request, sending the response, or the error in its place, through `server`. Requests for keys it
+    /// doesn't know are answered with `WireError::UnknownEndpoint`.
+    #[allow(async_fn_in_trait)]
+    async fn dispatch<S: Read + Write>(&mut self, server: &mut Server<S, READ_SIZE, WRITE_SIZE>, hdr: WireHeader, body: &[u8]) -> Result<(), Error<S::Error>>;
+}
+
+/// Receives COBS framed requests on a stream and sends the responses back on it. Frames may be up to `READ_SIZE`
+/// bytes from the host and `WRITE_SIZE` bytes to it.
+pub struct Server<S, const READ_SIZE: usize, const WRITE_SIZE: usize> {
+    stream: S,
+    frames: Frames<READ_SIZE>,
+}
+
+impl<S: Read + Write, const READ_SIZE: usize, const WRITE_SIZE: usize> Server<S, READ_SIZE, WRITE_SIZE> {
+    pub const fn new(stream: S) -> Self {
+        Self { stream, frames: Frames::new() }
+    }
+
+    pub fn stream(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    pub fn frames(&self) -> &Frames<READ_SIZE> {
+        &self.frames
+    }
+
+    /// Hands every request to `dispatch` until the stream ends or fails, returning why it stopped.
+    pub async fn serve(&mut self, dispatch: &mut impl Dispatch<READ_SIZE, WRITE_SIZE>) -> Error<S::Error> {
+        loop {
+            let frame = match self.receive().await {
+                Ok(frame) => frame,
+                Err(e) => return e,
+            };
+            let Ok((hdr, body)) = extract_header_from_bytes(&frame) else {
+                continue;
+            };
+            if let Err(e) = dispatch.dispatch(self, hdr, body).await {
+                return e;
+            }
+        }
+    }
+
+    /// Waits for the next frame, reading as much as the receive buffer has room for at a time.
+    pub async fn receive(&mut self) -> Result<Vec<u8, READ_SIZE>, Error<<S as ErrorType>::Error>> {
+        loop {
+            if let Some(frame) = self.frames.pop() {
+                return frame.map_err(|_| Error::Heapless);
+            }
+            // `pop` only runs out once every pending byte was fed, so the whole buffer is free to read into.
+            let pending = &mut self.frames.pending;
+            pending.resize_default(READ_SIZE).map_err(|_| Error::Heapless)?;
+            let read = self.stream.read(pending).await;
+            pending.truncate(*read.as_ref().unwrap_or(&0));
+            match read {
+                Ok(0) => return Err(Error::Closed),
+                Ok(_) => (),
+                Err(e) => return Err(Error::IO(e)),
+            }
+            self.frames.note_used();
+        }
+    }
+
+    pub async fn reply<E: Endpoint>(&mut self, seq_no: u32, msg: &E::Response) -> Result<(), Error<<S as ErrorType>::Error>>
+    where
+        E::Response: Serialize,
+    {
+        let mut buf = [0; WRITE_SIZE];
+        let data = headered::to_slice_cobs(seq_no, E::PATH, msg, &mut buf).map_err(Error::Postcard)?;
+        self.stream.write_all(data).await.map_err(Error::IO)
+    }
+
+    /// Sends the response, or the error in its place.
+    pub async fn respond<E: Endpoint>(&mut self, seq_no: u32, resp: Result<E::Response, WireError>) -> Result<(), Error<<S as ErrorType>::Error>>
+    where
+        E::Response: Serialize,
+    {
+        match resp {
+            Ok(msg) => self.reply::<E>(seq_no, &msg).await,
+            Err(e) => self.error(seq_no, e).await,
+        }
+    }
+
+    pub async fn error(&mut self, seq_no: u32, err: WireError) -> Result<(), Error<<S as ErrorType>::Error>> {
+        let mut buf = [0; WRITE_SIZE];
+        let data = headered::to_slice_cobs(seq_no, ERROR_PATH, &err, &mut buf).map_err(Error::Postcard)?;
+        self.stream.write_all(data).await.map_err(Error::IO)
+    }
+
+    pub async fn publish<T: Topic>(&mut self, seq_no: u32, msg: &T::Message) -> Result<(), Error<<S as ErrorType>::Error>>
+    where
+        T::Message: Serialize,
+    {
+        let mut buf = [0; WRITE_SIZE];
+        let data = headered::to_slice_cobs(seq_no, T::PATH, msg, &mut buf).map_err(Error::Postcard)?;
+        self.stream.write_all(data).await.map_err(Error::IO)
+    }
+}
+
+/// Deserializes a request body.
+pub fn decode<'a, T: Deserialize<'a>>(body: &'a [u8]) -> Result<T, WireError> {
+    postcard::from_bytes(body).map_err(|_| WireError::Decode)
+}
@@ -0,0 +1,126 @@
+use aoc_2023_icd::chunk::{Reassembler, LINE_SIZE};
+use aoc_2023_icd::error::WireError;
+use aoc_2023_icd::solver::{Answers, SolveReq, SolveResp, Solver};
+use aoc_2023_solvers::day1::Trebuchet;
+use aoc_2023_solvers::day2::CubeConundrum;
+use aoc_2023_solvers::day3::EngineSchematic;
+
+/// The days served by the combined firmware.
+pub const DAYS: &[u8] = &[1, 2, 3];
+
+/// Keeps one solver per day, so the host can switch between days without reflashing.
+#[derive(Debug, Default)]
+pub struct Solvers {
+    day1: Session<Trebuchet>,
+    day2: Session<CubeConundrum>,
+    day3: Session<EngineSchematic>,
+}
+
+impl Solvers {
+    pub const fn new() -> Self {
+        Self {
+            day1: Session::new(Trebuchet::new()),
+            day2: Session::new(CubeConundrum::new()),
+            day3: Session::new(EngineSchematic::new()),
+        }
+    }
+
+    /// Fails with `WireError::Internal` for days that aren't in [`DAYS`].
+    pub fn handle(&mut self, day: u8, req: SolveReq) -> Result<SolveResp, WireError> {
+        match day {
+            1 => self.day1.handle(req),
+            2 => self.day2.handle(req),
+            3 => self.day3.handle(req),
+            _ => Err(WireError::Internal),
+        }
+    }
+
+    /// Feeds one whole line to the day's solver, like a `SolveReq::Batch` of just that line.
+    pub fn feed(&mut self, day: u8, line: &str) -> Result<(), WireError> {
+        match day {
+            1 => self.day1.feed(line),
+            2 => self.day2.feed(line),
+            3 => self.day3.feed(line),
+            _ => Err(WireError::Internal),
+        }
+    }
+
+    /// The lines fed to the day's solver since the last reset, and the answers so far.
+    pub fn status(&self, day: u8) -> Option<(u32, Answers)> {
+        match day {
+            1 => Some(self.day1.status()),
+            2 => Some(self.day2.status()),
+            3 => Some(self.day3.status()),
+            _ => None,
+        }
+    }
+}
+
+/// A solver along with the number of lines fed since the last reset, so parse errors can point at the line.
+#[derive(Debug, Default)]
+struct Session<S> {
+    solver: S,
+    /// The token of the last reset.
+    session: u32,
+    line: u32,
+    chunks: Reassembler<LINE_SIZE>,
+}
+
+impl<S: Solver> Session<S> {
+    const fn new(solver: S) -> Self {
+        Self {
+            solver,
+            session: 0,
+            line: 0,
+            chunks: Reassembler::new(),
+        }
+    }
+
+    fn status(&self) -> (u32, Answers) {
+        (self.line, self.solver.answers())
+    }
+
+    fn feed(&mut self, line: &str) -> Result<(), WireError> {
+        feed(&mut self.solver, &mut self.line, line)
+    }
+
+    fn handle(&mut self, req: SolveReq) -> Result<SolveResp, WireError> {
+        match req {
+            SolveReq::Reset(session) => {
+                self.solver.reset();
+                self.session = session;
+                self.line = 0;
+                self.chunks.reset();
+            }
+            SolveReq::Line(chunk) => {
+                if let Some(line) = self.chunks.push(&chunk)? {
+                    feed(&mut self.solver, &mut self.line, line)?;
+                }
+            }
+            SolveReq::Batch(lines) => {
+                for line in lines.split('\n') {
+                    self.feed(line)?;
+                }
+            }
+            SolveReq::Finish => {
+                let answers = self.solver.finish().map_err(|e| e.at_line(self.line))?;
+                return Ok(SolveResp::Answers(answers));
+            }
+            SolveReq::Lines => {
+                return Ok(SolveResp::Lines {
+                    session: self.session,
+                    lines: self.line,
+                })
+            }
+        }
+        Ok(SolveResp::Ack)
+    }
+}
+
+/// Feeds a line to `solver`, counting it in `lines` only once it was taken, so an error points at the line after the
+/// last one counted.
+fn feed<S: Solver>(solver: &mut S, lines: &mut u32, line: &str) -> Result<(), WireError> {
+    solver.feed(line).map_err(|e| e.at_line(*lines + 1))?;
+    *lines += 1;
+    Ok(())
+}
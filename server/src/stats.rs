@@ -0,0 +1,58 @@
+use aoc_2023_icd::stats::{DayTiming, LinkStats, Stats, Timing};
+
+/// Adds up the time the board spends on requests, for the `GetStats` endpoint. The caller measures the time, since
+/// the firmware and the emulator keep it differently.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    stats: Stats,
+}
+
+impl Recorder {
+    pub const fn new() -> Self {
+        Self {
+            stats: Stats {
+                total: Timing { requests: 0, busy_us: 0 },
+                days: heapless::Vec::new(),
+                max_us: 0,
+                link: LinkStats {
+                    peak_used: 0,
+                    overfull: 0,
+                    corrupt: 0,
+                },
+                peak_stack: None,
+            },
+        }
+    }
+
+    /// Counts a request for `day` that took `busy_us` microseconds.
+    pub fn record(&mut self, day: u8, busy_us: u32) {
+        add(&mut self.stats.total, busy_us);
+        self.stats.max_us = self.stats.max_us.max(busy_us);
+        let days = &mut self.stats.days;
+        let i = match days.iter().position(|d| d.day == day) {
+            Some(i) => i,
+            None => {
+                let timing = DayTiming { day, timing: Timing::default() };
+                if days.push(timing).is_err() {
+                    return;
+                }
+                days.len() - 1
+            }
+        };
+        add(&mut days[i].timing, busy_us);
+    }
+
+    /// The counters so far, along with what the receive loop and the stack measurement report.
+    pub fn stats(&self, link: LinkStats, peak_stack: Option<u32>) -> Stats {
+        Stats {
+            link,
+            peak_stack,
+            ..self.stats.clone()
+        }
+    }
+}
+
+fn add(timing: &mut Timing, busy_us: u32) {
+    timing.requests = timing.requests.wrapping_add(1);
+    timing.busy_us += u64::from(busy_us);
+}
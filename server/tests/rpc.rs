@@ -0,0 +1,190 @@
+//! Drives the `aoc` image's dispatcher through the server loop on a tokio stream, like the host emulator does.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+
+use aoc_2023_icd::day1::Solve;
+use aoc_2023_icd::day2;
+use aoc_2023_icd::error::{WireError, ERROR_PATH};
+use aoc_2023_icd::log::{Level, LogRecord, LogTopic};
+use aoc_2023_icd::progress::ProgressTopic;
+use aoc_2023_icd::solver::{Answers, SolveReq, SolveResp};
+use aoc_2023_server::images::{Aoc, BoardServer, Build, Platform, WRITE_SIZE};
+use embedded_io_adapters::tokio_1::FromTokio;
+use postcard_rpc::accumulator::raw::{CobsAccumulator, FeedResult};
+use postcard_rpc::headered::extract_header_from_bytes;
+use postcard_rpc::{Endpoint, Key, Topic, WireHeader};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+#[derive(Default)]
+struct Logs(VecDeque<LogRecord>);
+
+impl Platform for Logs {
+    fn build(&self) -> Build {
+        Build {
+            version: "test",
+            git_hash: "test",
+            profile: "debug",
+        }
+    }
+
+    fn now_us(&self) -> u64 {
+        0
+    }
+
+    fn log(&mut self, level: Level, args: fmt::Arguments) {
+        let mut message = heapless::String::new();
+        let _ = message.write_fmt(args);
+        self.0.push_back(LogRecord { level, timestamp_ms: 0, message });
+    }
+
+    fn pop_log(&mut self) -> Option<LogRecord> {
+        self.0.pop_front()
+    }
+
+    fn peak_stack(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// The host end of a link to a fresh `aoc` image.
+struct Host {
+    stream: DuplexStream,
+    acc: CobsAccumulator<WRITE_SIZE>,
+    pending: VecDeque<(WireHeader, Vec<u8>)>,
+}
+
+impl Host {
+    fn spawn() -> Self {
+        let (stream, device) = tokio::io::duplex(WRITE_SIZE);
+        tokio::spawn(async move {
+            let mut server = BoardServer::new(FromTokio::new(device));
+            server.serve(&mut Aoc::new(Logs::default())).await;
+        });
+        Self {
+            stream,
+            acc: CobsAccumulator::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Sends the frames in a single write, so the board reads them all at once.
+    async fn send(&mut self, frames: &[(Key, u32, Vec<u8>)]) {
+        let mut bytes = Vec::new();
+        for (key, seq_no, body) in frames {
+            let mut frame = postcard::to_stdvec(&WireHeader { key: *key, seq_no: *seq_no }).unwrap();
+            frame.extend_from_slice(body);
+            bytes.extend(postcard::to_stdvec_cobs(&RawBytes(&frame)).unwrap());
+        }
+        self.stream.write_all(&bytes).await.unwrap();
+    }
+
+    /// The next frame that isn't published on a topic.
+    async fn response(&mut self) -> (WireHeader, Vec<u8>) {
+        let topics = [LogTopic::TOPIC_KEY, ProgressTopic::TOPIC_KEY];
+        loop {
+            while let Some((hdr, body)) = self.pending.pop_front() {
+                if !topics.contains(&hdr.key) {
+                    return (hdr, body);
+                }
+            }
+            let mut buf = [0; 1024];
+            let n = self.stream.read(&mut buf).await.unwrap();
+            assert!(n > 0, "the board hung up");
+            let mut window = &buf[..n];
+            while !window.is_empty() {
+                window = match self.acc.feed(window) {
+                    FeedResult::Consumed => &[],
+                    FeedResult::OverFull(_) | FeedResult::DeserError(_) => panic!("bad frame from the board"),
+                    FeedResult::Success { data, remaining } => {
+                        let (hdr, body) = extract_header_from_bytes(data).unwrap();
+                        self.pending.push_back((hdr, body.to_vec()));
+                        remaining
+                    }
+                };
+            }
+        }
+    }
+
+    async fn expect<T: DeserializeOwned>(&mut self, key: Key, seq_no: u32) -> T {
+        let (hdr, body) = self.response().await;
+        assert_eq!(hdr, WireHeader { key, seq_no });
+        postcard::from_bytes(&body).unwrap()
+    }
+}
+
+/// Bytes serialized as they are, without a length in front.
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeTuple;
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for byte in self.0 {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+fn error_key() -> Key {
+    Key::for_path::<WireError>(ERROR_PATH)
+}
+
+fn solve(seq_no: u32, req: &SolveReq) -> (Key, u32, Vec<u8>) {
+    (Solve::REQ_KEY, seq_no, postcard::to_stdvec(req).unwrap())
+}
+
+#[tokio::test]
+async fn unknown_key_is_answered_with_an_error() {
+    let mut host = Host::spawn();
+    host.send(&[(Key::for_path::<()>("nope"), 7, Vec::new())]).await;
+    assert_eq!(host.expect::<WireError>(error_key(), 7).await, WireError::UnknownEndpoint);
+}
+
+#[tokio::test]
+async fn bad_body_is_answered_with_an_error() {
+    let mut host = Host::spawn();
+    // There is no tenth variant of `SolveReq`.
+    host.send(&[(Solve::REQ_KEY, 3, vec![9])]).await;
+    assert_eq!(host.expect::<WireError>(error_key(), 3).await, WireError::Decode);
+    // The board goes on serving.
+    host.send(&[solve(4, &SolveReq::Reset(1))]).await;
+    assert_eq!(host.expect::<SolveResp>(Solve::RESP_KEY, 4).await, SolveResp::Ack);
+}
+
+#[tokio::test]
+async fn pipelined_frames_are_all_answered_in_order() {
+    let mut host = Host::spawn();
+    let lines = ["1abc2", "pqr3stu8vwx", "a1b2c3d4e5f", "treb7uchet"];
+    let mut frames = vec![solve(0, &SolveReq::Reset(1))];
+    for (i, line) in lines.iter().enumerate() {
+        frames.push(solve(i as u32 + 1, &SolveReq::Batch(heapless::String::from(*line))));
+    }
+    frames.push(solve(5, &SolveReq::Finish));
+    host.send(&frames).await;
+    for seq_no in 0..5 {
+        assert_eq!(host.expect::<SolveResp>(Solve::RESP_KEY, seq_no).await, SolveResp::Ack);
+    }
+    let answers = Answers { a: 142, b: Some(142) };
+    assert_eq!(host.expect::<SolveResp>(Solve::RESP_KEY, 5).await, SolveResp::Answers(answers));
+}
+
+#[tokio::test]
+async fn a_line_that_fails_is_not_counted() {
+    let mut host = Host::spawn();
+    let solve = |seq_no, req: &SolveReq| (day2::Solve::REQ_KEY, seq_no, postcard::to_stdvec(req).unwrap());
+    let batch = heapless::String::from("Game 1: 3 blue, 4 red, 1 green\nGame 2: x blue");
+    host.send(&[solve(0, &SolveReq::Reset(1)), solve(1, &SolveReq::Batch(batch)), solve(2, &SolveReq::Lines)]).await;
+    assert_eq!(host.expect::<SolveResp>(day2::Solve::RESP_KEY, 0).await, SolveResp::Ack);
+    assert_eq!(host.expect::<WireError>(error_key(), 1).await, WireError::Parse { line: 2, column: 8 });
+    assert_eq!(host.expect::<SolveResp>(day2::Solve::RESP_KEY, 2).await, SolveResp::Lines { session: 1, lines: 1 });
+    // The corrected line takes the place of the one that failed, as game 2.
+    let batch = heapless::String::from("Game 2: 1 blue, 2 green, 3 red");
+    host.send(&[solve(3, &SolveReq::Batch(batch)), solve(4, &SolveReq::Finish)]).await;
+    assert_eq!(host.expect::<SolveResp>(day2::Solve::RESP_KEY, 3).await, SolveResp::Ack);
+    let answers = Answers { a: 1 + 2, b: Some(12 + 6) };
+    assert_eq!(host.expect::<SolveResp>(day2::Solve::RESP_KEY, 4).await, SolveResp::Answers(answers));
+}
@@ -0,0 +1,109 @@
+use aoc_2023_icd::solver::{Answers, SolveError, Solver};
+
+const NUMBERS: &[&str] = &["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+
+#[derive(Debug, Default)]
+pub struct Trebuchet {
+    sum_a: u32,
+    sum_b: u32,
+}
+
+impl Trebuchet {
+    pub const fn new() -> Self {
+        Self { sum_a: 0, sum_b: 0 }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn feed(&mut self, line: &str) {
+        self.sum_a += calibration_value_a(line).unwrap_or(0);
+        self.sum_b += calibration_value_b(line).unwrap_or(0);
+    }
+
+    pub fn result_a(&self) -> u32 {
+        self.sum_a
+    }
+
+    pub fn result_b(&self) -> u32 {
+        self.sum_b
+    }
+}
+
+impl Solver for Trebuchet {
+    fn reset(&mut self) {
+        Trebuchet::reset(self)
+    }
+
+    fn feed(&mut self, line: &str) -> Result<(), SolveError> {
+        Trebuchet::feed(self, line);
+        Ok(())
+    }
+
+    fn answers(&self) -> Answers {
+        Answers {
+            a: self.sum_a,
+            b: Some(self.sum_b),
+        }
+    }
+
+    fn finish(&mut self) -> Result<Answers, SolveError> {
+        Ok(self.answers())
+    }
+}
+
+/// Combines the first and last digit of the line.
+pub fn calibration_value_a(line: &str) -> Option<u32> {
+    let (first, last) = first_last(&digits(line))?;
+    Some(first * 10 + last)
+}
+
+/// Combines the first and last digit of the line, where digits may also be spelled out.
+pub fn calibration_value_b(line: &str) -> Option<u32> {
+    let [first_num, last_num] = digits(line);
+    let first_letters = NUMBERS
+        .iter()
+        .enumerate()
+        .filter_map(|(n, s)| line.match_indices(s).min_by_key(|&(i, _)| i).map(|(i, _)| (i, n as u32)))
+        .min_by_key(|&(i, _)| i);
+    let last_letters = NUMBERS
+        .iter()
+        .enumerate()
+        .filter_map(|(n, s)| line.match_indices(s).max_by_key(|&(i, _)| i).map(|(i, _)| (i, n as u32)))
+        .max_by_key(|&(i, _)| i);
+    let (first, last) = first_last(&[first_num, last_num, first_letters, last_letters])?;
+    Some(first * 10 + last)
+}
+
+fn digits(line: &str) -> [Option<(usize, u32)>; 2] {
+    let mut numbers = line.char_indices().filter_map(|(i, c)| c.to_digit(10).map(|d| (i, d)));
+    let first = numbers.next();
+    let last = numbers.next_back();
+    [first, last]
+}
+
+fn first_last(items: &[Option<(usize, u32)>]) -> Option<(u32, u32)> {
+    let (_, first) = items.iter().filter_map(|&x| x).min_by_key(|&(i, _)| i)?;
+    let (_, last) = items.iter().filter_map(|&x| x).max_by_key(|&(i, _)| i)?;
+    Some((first, last))
+}
+
+#[cfg(test)]
+mod tests {
+    use aoc_2023_icd::solver::solve;
+
+    use super::*;
+
+    #[test]
+    fn example_a() {
+        let lines = ["1abc2", "pqr3stu8vwx", "a1b2c3d4e5f", "treb7uchet"];
+        assert_eq!(solve(&mut Trebuchet::new(), lines).unwrap().a, 142);
+    }
+
+    #[test]
+    fn example_b() {
+        let lines = ["two1nine", "eightwothree", "abcone2threexyz", "xtwone3four", "4nineeightseven2", "zoneight234", "7pqrstsixteen"];
+        assert_eq!(solve(&mut Trebuchet::new(), lines).unwrap().b, Some(281));
+    }
+}
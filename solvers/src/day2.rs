@@ -0,0 +1,147 @@
+use core::num::ParseIntError;
+use core::str::FromStr;
+
+use aoc_2023_icd::solver::{Answers, SolveError, Solver};
+
+/// The bag contents asked about in part A.
+pub const BAG: Game = Game { blue: 14, green: 13, red: 12 };
+
+#[derive(Debug)]
+pub struct CubeConundrum {
+    game_counter: u32,
+    result_a: u32,
+    result_b: u32,
+}
+
+impl CubeConundrum {
+    pub const fn new() -> Self {
+        Self {
+            game_counter: 1,
+            result_a: 0,
+            result_b: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Processes one `Game N: ...` line. Games are numbered by the order they are fed in.
+    ///
+    /// A parse error points at the start of the set of cubes that couldn't be parsed. The line isn't counted then, so
+    /// the corrected line is fed in its place.
+    pub fn feed(&mut self, line: &str) -> Result<(), SolveError> {
+        let id = self.game_counter;
+        if let Some((_, games)) = line.split_once(": ") {
+            let mut possible = true;
+            let mut min_set = Game::default();
+            for game in games.split("; ") {
+                let column = game.as_ptr() as usize - line.as_ptr() as usize;
+                let game: Game = game.parse().map_err(|_| SolveError::Parse { column: column as u32 })?;
+                possible &= game.fits_in(&BAG);
+                min_set = min_set.max(&game);
+            }
+            // A
+            if possible {
+                self.result_a += id;
+            }
+            // B
+            self.result_b += min_set.power();
+        }
+        self.game_counter += 1;
+        Ok(())
+    }
+
+    pub fn results(&self) -> (u32, u32) {
+        (self.result_a, self.result_b)
+    }
+}
+
+impl Default for CubeConundrum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver for CubeConundrum {
+    fn reset(&mut self) {
+        CubeConundrum::reset(self)
+    }
+
+    fn feed(&mut self, line: &str) -> Result<(), SolveError> {
+        CubeConundrum::feed(self, line)
+    }
+
+    fn answers(&self) -> Answers {
+        Answers {
+            a: self.result_a,
+            b: Some(self.result_b),
+        }
+    }
+
+    fn finish(&mut self) -> Result<Answers, SolveError> {
+        Ok(self.answers())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Game {
+    pub blue: u32,
+    pub green: u32,
+    pub red: u32,
+}
+
+impl Game {
+    pub fn fits_in(&self, bag: &Game) -> bool {
+        self.blue <= bag.blue && self.green <= bag.green && self.red <= bag.red
+    }
+
+    /// The smallest set of cubes that could have produced both `self` and `other`.
+    pub fn max(&self, other: &Game) -> Game {
+        Game {
+            blue: self.blue.max(other.blue),
+            green: self.green.max(other.green),
+            red: self.red.max(other.red),
+        }
+    }
+
+    pub fn power(&self) -> u32 {
+        self.blue * self.green * self.red
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut game: Game = Default::default();
+        for color in s.split(", ") {
+            match color.trim().split_once(' ') {
+                Some((n, "blue")) => game.blue = n.parse()?,
+                Some((n, "green")) => game.green = n.parse()?,
+                Some((n, "red")) => game.red = n.parse()?,
+                _ => (),
+            }
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aoc_2023_icd::solver::solve;
+
+    use super::*;
+
+    #[test]
+    fn example() {
+        let lines = [
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green",
+            "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue",
+            "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red",
+            "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red",
+            "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green",
+        ];
+        assert_eq!(solve(&mut CubeConundrum::new(), lines).unwrap(), Answers { a: 8, b: Some(2286) });
+    }
+}
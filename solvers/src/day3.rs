@@ -0,0 +1,298 @@
+use aoc_2023_icd::day3::{Coord, Number, Symbol, GEARS_CAPACITY, LINE_CAPACITY, PARTS_CAPACITY};
+use aoc_2023_icd::solver::{Answers, SolveError, Solver};
+use heapless::Vec;
+
+pub type Numbers = Vec<Number, LINE_CAPACITY>;
+pub type Symbols = Vec<Symbol, LINE_CAPACITY>;
+pub type Parts = Vec<Number, PARTS_CAPACITY>;
+pub type GearRatios = Vec<u32, GEARS_CAPACITY>;
+
+/// What a line confirmed about itself and the line before it.
+#[derive(Debug, Default, PartialEq)]
+pub struct Confirmed {
+    pub parts: Parts,
+    /// Ratios of the gears on the previous line, which can only be told apart from other `*` once the line after
+    /// them is known.
+    pub gears: GearRatios,
+}
+
+/// Streams an engine schematic one line at a time, keeping only the previous line around.
+#[derive(Debug, Default)]
+pub struct EngineSchematic {
+    /// Lines fed so far, which is also the row of the next one.
+    lines: u32,
+    prev_line: Option<Line>,
+    sum_a: u32,
+    sum_b: u32,
+}
+
+#[derive(Debug)]
+struct Line {
+    numbers: Numbers,
+    symbols: Symbols,
+    /// Whether the number at the same index has already been reported as a part.
+    counted: Vec<bool, LINE_CAPACITY>,
+    /// Every `*` on the line, with the numbers seen next to it so far.
+    gears: Vec<Gear, GEARS_CAPACITY>,
+}
+
+/// A `*` that is a gear if exactly two numbers end up next to it.
+#[derive(Debug, Clone)]
+struct Gear {
+    symbol: Symbol,
+    adjacent: u8,
+    /// `None` once the product no longer fits, which only matters if this turns out to be a gear.
+    ratio: Option<u32>,
+}
+
+impl Gear {
+    fn new(symbol: Symbol) -> Self {
+        Self {
+            symbol,
+            adjacent: 0,
+            ratio: Some(1),
+        }
+    }
+
+    /// Counts the numbers next to the gear on the line above, below, or its own.
+    fn add(&mut self, numbers: &[Number]) {
+        for n in numbers.iter().filter(|n| is_adjacent(n, &self.symbol)) {
+            self.adjacent = self.adjacent.saturating_add(1);
+            self.ratio = self.ratio.and_then(|r| r.checked_mul(n.value));
+        }
+    }
+
+    fn ratio(&self) -> Result<Option<u32>, SolveError> {
+        match self.adjacent {
+            2 => self.ratio.map(Some).ok_or(SolveError::Capacity),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl EngineSchematic {
+    pub const fn new() -> Self {
+        Self {
+            lines: 0,
+            prev_line: None,
+            sum_a: 0,
+            sum_b: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns the part numbers that are confirmed by the given line, which includes numbers on the
+    /// previous line that are adjacent to a symbol on this one, and the gears on the previous line. Every part
+    /// and gear is reported exactly once.
+    ///
+    /// Fails with `SolveError::Capacity` if the line doesn't fit, in which case it is as if it had never been fed.
+    pub fn feed(&mut self, line: &str) -> Result<Confirmed, SolveError> {
+        let y = coord(self.lines as usize)?;
+        let (numbers, symbols) = scan_line(line, y)?;
+        let prev_line = self.prev_line.as_ref();
+        let prev_symbols = prev_line.map(|l| &l.symbols[..]).unwrap_or(&[]);
+        let prev_numbers = prev_line.map(|l| &l.numbers[..]).unwrap_or(&[]);
+
+        let mut gears = Vec::new();
+        for s in symbols.iter().filter(|s| s.symbol == '*') {
+            let mut gear = Gear::new(s.clone());
+            gear.add(prev_numbers);
+            gear.add(&numbers);
+            gears.push(gear).map_err(|_| SolveError::Capacity)?;
+        }
+        let mut gear_ratios = GearRatios::new();
+        for mut gear in prev_line.iter().flat_map(|l| l.gears.iter().cloned()) {
+            gear.add(&numbers);
+            if let Some(ratio) = gear.ratio()? {
+                gear_ratios.push(ratio).map_err(|_| SolveError::Capacity)?;
+            }
+        }
+
+        let mut parts = Parts::new();
+        let mut counted = Vec::new();
+        for n in numbers.iter() {
+            let is_part = symbols.iter().chain(prev_symbols).any(|s| is_adjacent(n, s));
+            if is_part {
+                parts.push(n.clone()).map_err(|_| SolveError::Capacity)?;
+            }
+            counted.push(is_part).map_err(|_| SolveError::Capacity)?;
+        }
+        if let Some(prev) = prev_line {
+            let uncounted = prev.numbers.iter().zip(&prev.counted).filter(|(_, counted)| !**counted);
+            for (n, _) in uncounted.filter(|(n, _)| symbols.iter().any(|s| is_adjacent(n, s))) {
+                parts.push(n.clone()).map_err(|_| SolveError::Capacity)?;
+            }
+        }
+
+        let sum_a = add_all(self.sum_a, parts.iter().map(|n| n.value))?;
+        let sum_b = add_all(self.sum_b, gear_ratios.iter().copied())?;
+        self.sum_a = sum_a;
+        self.sum_b = sum_b;
+        self.lines += 1;
+        self.prev_line.replace(Line {
+            numbers,
+            symbols,
+            counted,
+            gears,
+        });
+        Ok(Confirmed { parts, gears: gear_ratios })
+    }
+
+    /// Returns the ratios of the gears on the last line, which are settled if no more lines follow. Lines fed
+    /// afterwards still count the gears on it, as if this hadn't been called.
+    pub fn finish(&self) -> Result<GearRatios, SolveError> {
+        let mut ratios = GearRatios::new();
+        for gear in self.prev_line.iter().flat_map(|l| l.gears.iter()) {
+            if let Some(ratio) = gear.ratio()? {
+                ratios.push(ratio).map_err(|_| SolveError::Capacity)?;
+            }
+        }
+        Ok(ratios)
+    }
+}
+
+impl Solver for EngineSchematic {
+    fn reset(&mut self) {
+        EngineSchematic::reset(self)
+    }
+
+    fn feed(&mut self, line: &str) -> Result<(), SolveError> {
+        EngineSchematic::feed(self, line).map(drop)
+    }
+
+    fn answers(&self) -> Answers {
+        Answers {
+            a: self.sum_a,
+            b: Some(self.sum_b),
+        }
+    }
+
+    fn finish(&mut self) -> Result<Answers, SolveError> {
+        let last = EngineSchematic::finish(self)?;
+        Ok(Answers {
+            a: self.sum_a,
+            b: Some(add_all(self.sum_b, last.iter().copied())?),
+        })
+    }
+}
+
+/// Finds all numbers and symbols on a single line of the schematic.
+///
+/// A number's `x` is the half-open column range `(start, end)` it covers.
+pub fn scan_line(line: &str, y: Coord) -> Result<(Numbers, Symbols), SolveError> {
+    let mut numbers = Numbers::new();
+    let mut symbols = Symbols::new();
+    // Start column and value of the number being scanned.
+    let mut current_number: Option<(usize, u32)> = None;
+
+    let mut flush = |current_number: &mut Option<(usize, u32)>, end: usize| -> Result<(), SolveError> {
+        if let Some((start, value)) = current_number.take() {
+            let x = (coord(start)?, coord(end)?);
+            numbers.push(Number { x, y, value }).map_err(|_| SolveError::Capacity)?;
+        }
+        Ok(())
+    };
+
+    for (x, c) in line.char_indices() {
+        if let Some(digit) = c.to_digit(10) {
+            let (start, value) = current_number.unwrap_or((x, 0));
+            let value = value.checked_mul(10).and_then(|v| v.checked_add(digit)).ok_or(SolveError::Capacity)?;
+            current_number = Some((start, value));
+            continue;
+        } else if c != '.' {
+            symbols.push(Symbol { x: coord(x)?, y, symbol: c }).map_err(|_| SolveError::Capacity)?;
+        }
+        flush(&mut current_number, x)?;
+    }
+    flush(&mut current_number, line.len())?;
+
+    Ok((numbers, symbols))
+}
+
+/// Whether the symbol touches the number, including diagonally, ignoring rows.
+pub fn is_adjacent(n: &Number, s: &Symbol) -> bool {
+    (n.x.0..=n.x.1).contains(&s.x.saturating_add(1)) || (n.x.0..=n.x.1).contains(&s.x)
+}
+
+fn coord(x: usize) -> Result<Coord, SolveError> {
+    Coord::try_from(x).map_err(|_| SolveError::Capacity)
+}
+
+fn add_all(sum: u32, mut values: impl Iterator<Item = u32>) -> Result<u32, SolveError> {
+    values.try_fold(sum, |sum, v| sum.checked_add(v)).ok_or(SolveError::Capacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use aoc_2023_icd::solver::solve;
+
+    use super::*;
+
+    const EXAMPLE: [&str; 10] = [
+        "467..114..",
+        "...*......",
+        "..35..633.",
+        "......#...",
+        "617*......",
+        ".....+.58.",
+        "..592.....",
+        "......755.",
+        "...$.*....",
+        ".664.598..",
+    ];
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(&mut EngineSchematic::new(), EXAMPLE).unwrap(), Answers { a: 4361, b: Some(467835) });
+    }
+
+    #[test]
+    fn number_at_the_end_of_a_line() {
+        let (numbers, symbols) = scan_line("*..35", 2).unwrap();
+        assert_eq!(&numbers[..], &[Number { x: (3, 5), y: 2, value: 35 }]);
+        assert_eq!(&symbols[..], &[Symbol { x: 0, y: 2, symbol: '*' }]);
+    }
+
+    #[test]
+    fn number_too_big() {
+        assert_eq!(scan_line("..4294967296", 0), Err(SolveError::Capacity));
+    }
+
+    #[test]
+    fn too_many_symbols() {
+        let mut line = heapless::String::<{ LINE_CAPACITY + 1 }>::new();
+        for _ in 0..=LINE_CAPACITY {
+            line.push('#').unwrap();
+        }
+        assert_eq!(scan_line(&line, 0), Err(SolveError::Capacity));
+    }
+
+    #[test]
+    fn capacity_error_leaves_the_schematic_as_it_was() {
+        let mut schematic = EngineSchematic::new();
+        schematic.feed("467..114..").unwrap();
+        assert_eq!(schematic.feed("...*..99999999999"), Err(SolveError::Capacity));
+        for line in &EXAMPLE[1..] {
+            schematic.feed(line).unwrap();
+        }
+        assert_eq!(Solver::finish(&mut schematic).unwrap(), Answers { a: 4361, b: Some(467835) });
+    }
+
+    #[test]
+    fn finish_in_the_middle_changes_nothing() {
+        for split in 0..EXAMPLE.len() {
+            let mut schematic = EngineSchematic::new();
+            for line in &EXAMPLE[..split] {
+                schematic.feed(line).unwrap();
+            }
+            Solver::finish(&mut schematic).unwrap();
+            for line in &EXAMPLE[split..] {
+                schematic.feed(line).unwrap();
+            }
+            assert_eq!(Solver::finish(&mut schematic).unwrap(), Answers { a: 4361, b: Some(467835) }, "finished after {split} lines");
+        }
+    }
+}
@@ -0,0 +1,5 @@
+#![no_std]
+
+pub mod day1;
+pub mod day2;
+pub mod day3;